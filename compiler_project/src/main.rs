@@ -1,20 +1,26 @@
 /// Crates that are used to support the implementation of Lolcode compiler
 /***
  * Regex will be used to validate URLS, variable names, variable definitions and text conventions
- * arch::x86_64 has been used to built a binary optimized for x86 architectures (optimized support for chrome)
  * Hashmap will be used to store variable name key pairs within their scopes
+ * Fmt - used to format compile errors the same way everywhere
  * Fs - file crate used to getting input from file and appending content to a file
  * Env - used to collect command line arguments from the program
  * Process - provide standardized system errors 
  * Path - Handle system file paths for opening files in chrome (copied from the chatgpt response provided by professor)
+ * Time - Instant used to time the compilation of a directory of files
+ * Zip - zip crate used to write --bundle archives of the html page and its local media
  * 
  */
 use regex::Regex;
-use std::arch::x86_64::CpuidResult;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::{File, read_to_string};
 use std::{env, process, vec, io};
-use std::{fs, path::Path, process::Command};
+use std::io::Write;
+use std::{fs, path::Path, path::PathBuf, process::Command};
+use std::time::Instant;
+use std::ops::Range;
+use std::cell::RefCell;
 
 
 /**
@@ -34,13 +40,64 @@ use winreg::RegKey;
  * 3. contains a current token variable (will keep track of tokens collected from program string)
  * 4. Scope stack - Will keep track of variables and their scopes
  * 5. Language tokens - Used to store tokens and their line numbers for parsing
+ * 6. Tag map - Used to look up the output tag of each element during HTML conversion
+ * 7. Shorthand - Used to let the lexer accept the compact keyword set (#p ... #/p) next to the regular keywords
+ * 8. Source comments - Used to mark each emitted block with the source line it came from (<!-- src:N -->)
+ * 9. Allowed schemes - URL schemes accepted in audio and video addresses, javascript: is never in the default list
+ * 10. Strip comments - Used to leave #obtw ... #tldr comments out of the HTML output instead of emitting <!-- -->
+ * 11. Asset urls - Local media addresses mapped to the address they are written as, used by --bundle to point into the zip
+ * 12. Pedantic - Used to turn discouraged patterns (uppercase tags, repeated newlines, empty blocks, unused variables) into errors
+ * 13. Document - the tree built by the parser, rendered by to_html
+ * 14. Errors - Syntax and semantic errors recorded while parsing, all of them are reported once parsing is done
+ * 15. Warnings - Problems that do not stop the compiler, like variables that are declared but never used
+ * 16. Current line and current column - position of the current token, updated by next_token every time a token is consumed
+ * 17. Lang - language of the page, written as the lang attribute of <html>, en by default
+ * 18. Strict - Used to report unknown # tags with the list of valid tags
+ * 19. Fragment - Used to emit only the body content, without the doctype, html and head, so it can be embedded in an existing page
+ * 20. Keywords - Keywords of the language the lexer is created with, the regular keywords unless a dialect renames them
+ * 21. Allow shadowing - Used to let a paragraf declare a variable with the name of one in an outer scope, true unless --no-shadowing is used
+ * 22. Minify - Used to emit the HTML on a single line, without the indentation and the newlines between tags
+ * 23. Declarations - every variable declared with its value, line and scope depth, kept after the scopes are popped for --json and declared_variables
+ * 24. Source map - the byte range of the html of every block next to the source line it came from, filled while rendering
+ * 25. Max depth - the deepest blocks and inline elements can be nested, DEFAULT_MAX_DEPTH unless --max-depth is given
+ * 26. Pedantic notes - the line and message of the empty blocks and elements and the repeated newlines the parser found, reported as errors in --pedantic mode
+ * 27. Unused variables - the name and line of every variable that was never used, collected with the warnings about them
+ * 28. Annotate - Used to write the source comments as <!-- line N --> for --annotate instead of <!-- src:N -->
+ * 29. Img responsive - Used to let every image shrink to the width of the page for --img-responsive
+ * 30. Default title - the title of a page without a title or headings, empty unless --default-title is given
  */
 pub struct LolcodeCompiler {
     lexer: LolcodeLexicalAnalyzer,
     parser: LolcodeSyntaxAnalyzer,
     current_tok: String,
     scope_stack: Vec<HashMap<String, VariableInfo>>,
-    language_tokens: Vec<(String, usize)>,
+    language_tokens: Vec<(String, usize, usize)>,
+    tag_map: TagMap,
+    shorthand: bool,
+    source_comments: bool,
+    allowed_schemes: Vec<String>,
+    strip_comments: bool,
+    asset_urls: HashMap<String, String>,
+    pedantic: bool,
+    document: Option<HtmlNode>,
+    errors: Vec<CompileError>,
+    warnings: Vec<String>,
+    current_line: usize,
+    current_column: usize,
+    lang: String,
+    strict: bool,
+    fragment: bool,
+    keywords: KeywordConfig,
+    allow_shadowing: bool,
+    minify: bool,
+    declarations: Vec<(String, Option<String>, usize, usize)>,
+    source_map: RefCell<SourceMap>,
+    max_depth: usize,
+    pedantic_notes: Vec<(usize, String)>,
+    unused_variables: Vec<(String, usize)>,
+    annotate: bool,
+    img_responsive: bool,
+    default_title: String,
 }
 
 /**
@@ -56,11 +113,368 @@ pub struct LolcodeCompiler {
  * 1. Name - consists of the name of the variable
  * 2. value - consists of the value of the variable
  * 3. line_defined - consists of the line where the variable is defined 
+ * 4. used - set once the variable is used with #lemme see, unused variables are warned about when their scope ends
+ * 5. kind - whether the value is text or a whole number, only numbers can be added with #gimmeh maths
  */
 struct VariableInfo {
     name: String,
     value: Option<String>,
     line_defined: usize,
+    used: bool,
+    kind: VarKind,
+}
+
+/**
+ * 1. #[derive(Clone, Copy)] - procedural macro to allow VarKind to be copied out of VariableInfo
+ * 2. #[derive(Debug, PartialEq)] - procedural macro to allow VarKind to be printed and compared
+
+ */
+#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq)]
+
+/**
+ * Var Kind enum - the type of a variable value, classified when the variable is declared
+ * 1. Text - any value that is not a whole number, and variables without a value
+ * 2. Number - a value made of digits only, with the number it stands for
+ */
+enum VarKind {
+    Text,
+    Number(u64),
+}
+
+/**
+ * 1. #[derive(Clone, Copy)] - procedural macro to allow AssetKind to be copied out of the asset list
+ * 2. #[derive(Debug, PartialEq)] - procedural macro to allow AssetKind to be printed and compared
+
+ */
+#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq)]
+
+/**
+ * Asset Kind enum - the element a local asset address was found in, used to bundle or inline the asset
+ * 1. Audio - an address of a soundz element
+ * 2. Video - the address of a vidz element
+ * 3. Image - the address of a piccy element
+ */
+enum AssetKind {
+    Audio,
+    Video,
+    Image,
+}
+
+// Classify a variable value, digits only are a number unless the number is too large to add
+fn classify_value(value: Option<&str>) -> VarKind {
+    let number_rule = Regex::new(r"^[0-9]+$").unwrap();
+    match value {
+        Some(value) if number_rule.is_match(value) => value.parse().map(VarKind::Number).unwrap_or(VarKind::Text),
+        _ => VarKind::Text,
+    }
+}
+
+/**
+ * Tag Map struct - maps each lolcode element to the tag it is rendered as during HTML conversion
+ * Defaults reproduce the HTML output, a --tag-map file of element=tag lines can override them for other targets
+ * 1. tags - element keyword (paragraf, bold, ...) mapped to the output tag name (p, b, ...)
+ */
+pub struct TagMap {
+    tags: HashMap<String, String>,
+}
+
+impl Default for TagMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TagMap {
+    pub fn new() -> Self {
+        let defaults = [
+            ("head", "head"),
+            ("title", "title"),
+            ("paragraf", "p"),
+            ("bold", "b"),
+            ("italics", "i"),
+            ("list", "ul"),
+            ("item", "li"),
+            ("newline", "br"),
+            ("soundz", "audio"),
+            ("vidz", "iframe"),
+            ("bighead", "h1"),
+            ("medhead", "h2"),
+            ("lilhead", "h3"),
+            ("smolhead", "h4"),
+            ("tinyhead", "h5"),
+            ("teenyhead", "h6"),
+            ("linkz", "a"),
+            ("stylez", "span"),
+            ("grid", "table"),
+            ("roe", "tr"),
+            ("sel", "td"),
+            ("divider", "hr"),
+            ("saidz", "blockquote"),
+            ("codez", "code"),
+            ("shout", "strong"),
+            ("piccy", "img"),
+            ("showcase", "figure"),
+            ("boxz", "div"),
+        ];
+
+        Self {
+            tags: defaults
+                .iter()
+                .map(|(element, tag)| (element.to_string(), tag.to_string()))
+                .collect(),
+        }
+    }
+
+    // Read element=tag lines from a file, warn about invalid mappings and keep the default for them
+    pub fn load(path: &str) -> Self {
+        let mut tag_map = TagMap::new();
+
+        //If the file cannot be read, warn and use the default mapping
+        let contents = match read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Warning: could not read tag map '{}': {}. Using default tags.", path, e);
+                return tag_map;
+            }
+        };
+
+        for (index, line) in contents.lines().enumerate() {
+            tag_map.apply_line(line, index + 1);
+        }
+
+        tag_map
+    }
+
+    // Apply a single element=tag line to the map, blank lines are skipped
+    fn apply_line(&mut self, line: &str, line_number: usize) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        // Expect element=tag, report a warning if the line has no '='
+        let Some((element, tag)) = line.split_once('=') else {
+            eprintln!("Warning: tag map line {}: expected 'element=tag', found '{}'.", line_number, line);
+            return;
+        };
+
+        let element = element.trim().to_lowercase();
+        let tag = tag.trim();
+
+        // Only elements known to the language can be remapped
+        if !self.tags.contains_key(&element) {
+            eprintln!("Warning: tag map line {}: unknown element '{}', mapping ignored.", line_number, element);
+            return;
+        }
+
+        // Tag names must start with a letter and only contain letters, digits and dashes
+        let valid_tag = Regex::new(r"^[A-Za-z][A-Za-z0-9\-]*$").unwrap();
+        if !valid_tag.is_match(tag) {
+            eprintln!(
+                "Warning: tag map line {}: invalid tag '{}' for '{}', keeping '{}'.",
+                line_number, tag, element, self.tags[&element]
+            );
+            return;
+        }
+
+        self.tags.insert(element, tag.to_string());
+    }
+
+    // Return the output tag for an element
+    pub fn tag<'a>(&'a self, element: &'a str) -> &'a str {
+        self.tags.get(element).map(|tag| tag.as_str()).unwrap_or(element)
+    }
+}
+
+/**
+ * Compile Error enum - every error the compiler reports, formatted the same way by Display
+ * 1. Lexical - a token that is not a lexeme of the language, with the closest tag for a # token that is a typo of one
+ * 2. Syntax - a grammar expectation that failed, with the tokens that were expected and the token found
+ * 3. Semantic - a variable or URL rule that failed, with a message
+ * 4. Structure - a document level problem such as nesting or extra tokens, with a message
+ * 5. Pedantic - a tolerated but discouraged pattern, only reported in --pedantic mode
+ * 6. UnresolvedVariable - a variable use that was never declared, with the variable name, found by the parser or during HTML conversion of a tree without the value
+ * 7. UnknownTag - a # token that is not a tag, only reported in --strict mode, with the tags that are valid
+ * 8. Unterminated - a block or element the input ended in, with the line it starts at
+ */
+#[derive(Debug)]
+pub enum CompileError {
+    Lexical { line: usize, column: usize, token: String, suggestion: Option<String> },
+    Syntax { line: usize, column: usize, expected: Vec<String>, found: String },
+    Semantic { line: usize, message: String },
+    Structure { line: usize, message: String },
+    Pedantic { line: usize, message: String },
+    UnresolvedVariable { line: usize, name: String },
+    UnknownTag { line: usize, column: usize, token: String, valid: Vec<String> },
+    Unterminated { line: usize, block: String },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Lexical { line, column, token, suggestion } => {
+                write!(f, "Lexical error at line {}, column {}: '{}' is not a recognized token.", line, column, token)?;
+                match suggestion {
+                    Some(tag) => write!(f, " Did you mean '{}'?", tag),
+                    None => Ok(()),
+                }
+            }
+
+            //An empty found token means the input ran out
+            CompileError::Syntax { line, column, expected, found } => {
+                let found = if found.is_empty() {
+                    "end of input".to_string()
+                } else {
+                    format!("'{}'", found)
+                };
+                write!(
+                    f,
+                    "Syntax error at line {}, column {}: expected one of [{}], found {}.",
+                    line,
+                    column,
+                    expected.join(", "),
+                    found
+                )
+            }
+            CompileError::Semantic { line, message } => {
+                write!(f, "Semantic error at line {}: {}", line, message)
+            }
+            CompileError::Structure { line, message } => {
+                write!(f, "Syntax error at line {}: {}", line, message)
+            }
+            CompileError::Pedantic { line, message } => {
+                write!(f, "Pedantic error at line {}: {}", line, message)
+            }
+            CompileError::UnresolvedVariable { line, name } => {
+                write!(
+                    f,
+                    "Semantic error at line {}: Variable '{}' is used before being defined.\n  --> Variable '{}' has not been declared in the current scope.\n  --> Use '#I HAZ {}' to declare the variable before using it.",
+                    line, name, name, name
+                )
+            }
+            CompileError::UnknownTag { line, column, token, valid } => {
+                write!(
+                    f,
+                    "Lexical error at line {}, column {}: unknown tag '{}', the valid tags are [{}].",
+                    line,
+                    column,
+                    token,
+                    valid.join(", ")
+                )
+            }
+            CompileError::Unterminated { line, block } => {
+                write!(f, "Syntax error: Unterminated {} starting at line {}, the input ended before it was closed.", block, line)
+            }
+        }
+    }
+}
+
+// Build the error for a failed grammar expectation, used by all the parse_* checks
+fn syntax_error(line: usize, column: usize, expected: &[&str], found: &str) -> CompileError {
+    CompileError::Syntax {
+        line,
+        column,
+        expected: expected.iter().map(|token| token.to_string()).collect(),
+        found: found.to_string(),
+    }
+}
+
+// The two words of a variable tag in the keywords of the lexer, e.g. #i and haz of #i haz, the second is empty for a one word tag
+fn tag_words(keywords: &[String]) -> [String; 2] {
+    [0, 1].map(|position| keywords.get(position).cloned().unwrap_or_default())
+}
+
+// Report a syntax error at the end of input as the block it was found in being unterminated
+// A nested block converts the error first, so the innermost unterminated block is reported
+fn unterminated(error: CompileError, block: &str, line: usize) -> CompileError {
+    match error {
+        CompileError::Syntax { found, .. } if found.is_empty() => CompileError::Unterminated {
+            line,
+            block: block.to_string(),
+        },
+        error => error,
+    }
+}
+
+// Compile a lolcode program string into html with the default settings, without reading files or opening a browser
+// Returns the first error found
+pub fn compile_str(source: &str) -> Result<String, CompileError> {
+    LolcodeCompiler::new()
+        .compile_to_html(source)
+        .map_err(|mut errors| errors.remove(0))
+}
+
+// Check a lolcode program string with the default settings without converting it to html, for linting in an editor or CI
+// Returns the first error found
+pub fn check_str(source: &str) -> Result<(), CompileError> {
+    LolcodeCompiler::new()
+        .check(source)
+        .map_err(|mut errors| errors.remove(0))
+}
+
+// Print a compile error and stop the compiler, only used by main, the compiler itself returns its errors
+fn report(error: CompileError) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+/**
+ * 1. #[derive(Clone, Debug, PartialEq)] - procedural macro to allow HtmlNode trees to be copied, printed and compared
+
+ */
+#[derive(Clone, Debug, PartialEq)]
+
+/**
+ * Html Node enum - the document tree built by the parse_* methods and rendered by to_html
+ * 1. Document - the page between #hai and #kthxbye, comments, head and body content in order
+ * 2. Head, Paragraph, List, Heading, Quote - blocks opened with #maek, with the line of their element for source comments, headings keep their level 1 to 6
+ * 3. Title, Item, Bold, Italics, Shout - elements opened with #gimmeh and closed with #mkay
+ * 4. Newline, HRule, Audio, Video - elements without text, audio keeps every address in order
+ * 5. Link - a linkz element, the address and the link text
+ * 6. Text - a run of text tokens separated by spaces
+ * 7. VarUse - a variable use, with the value the variable had in its scope when it was parsed
+ * 8. Comment - the text of a #obtw ... #tldr comment
+ * 9. Description - the text of the describ element of the head
+ * 10. Styled - a stylez element, the CSS class and the inline contents it applies to
+ * 11. Stylesheet - the address of a dressup element of the head
+ * 12. Table, Row, Cell - a grid block with the line of its element, the roe blocks inside it and the sel cells of each row
+ * 13. Code - the text of a codez element, with the spacing it was written with
+ * 14. Image - a piccy element, the address and the alt text
+ * 15. Figure - a showcase block with the line of its element, its image and the caption after the image
+ * 16. Container - a boxz block with the line of its element, its optional CSS class (written .name) and the body content inside it
+ */
+pub enum HtmlNode {
+    Document(Vec<HtmlNode>),
+    Head { line: usize, children: Vec<HtmlNode> },
+    Title(Vec<HtmlNode>),
+    Paragraph { line: usize, children: Vec<HtmlNode> },
+    Quote { line: usize, children: Vec<HtmlNode> },
+    List { line: usize, children: Vec<HtmlNode> },
+    Heading { level: usize, line: usize, children: Vec<HtmlNode> },
+    Item(Vec<HtmlNode>),
+    Bold(Vec<HtmlNode>),
+    Italics(Vec<HtmlNode>),
+    Shout(Vec<HtmlNode>),
+    Newline,
+    HRule,
+    Audio(Vec<String>),
+    Video(String),
+    Link { address: String, text: String },
+    Text(String),
+    VarUse { name: String, value: Option<String>, line: usize },
+    Comment(String),
+    Description(String),
+    Styled { class: String, children: Vec<HtmlNode> },
+    Stylesheet(String),
+    Table { line: usize, children: Vec<HtmlNode> },
+    Row(Vec<HtmlNode>),
+    Cell(Vec<HtmlNode>),
+    Code(String),
+    Image { address: String, alt: String },
+    Figure { line: usize, image: Box<HtmlNode>, caption: Vec<HtmlNode> },
+    Container { line: usize, class: Option<String>, children: Vec<HtmlNode> },
 }
 
 /**
@@ -91,14 +505,117 @@ pub trait LexicalAnalyzer {
     fn lookup(&self, s: &str) -> bool;
 }
 
+/**
+ * 1. #[derive(Clone, Copy)] - procedural macro to allow TokenKind to be copied when classifying tokens
+ * 2. #[derive(Debug, PartialEq)] - procedural macro to allow TokenKind to be printed and compared
+
+ */
+#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq)]
+
+/**
+ * Token Kind enum - the kind of lexeme a token is, used by editor tools to validate and highlight single tokens
+ * 1. Tag kinds - #hai, #kthxbye, #obtw, #tldr, #maek, #oic, #gimmeh, #mkay and the variable keywords
+ * 2. Element - element keywords like head, paragraf, bold
+ * 3. Identifier, Text, Address - tokens matching the variable name, text and URL rules
+ */
+pub enum TokenKind {
+    DocumentStart,
+    DocumentEnd,
+    CommentStart,
+    CommentEnd,
+    MakeStart,
+    OicEnd,
+    GimmehStart,
+    MkayEnd,
+    VariableStart,
+    VariableMid,
+    VariableUse,
+    Element,
+    Identifier,
+    Text,
+    Address,
+}
+
+/**
+ * Token Stream struct - the tokens of the program in document order, consumed from the front by the parser
+ * 1. tokens - tuples of token, line number and column number
+ * 2. spans - byte range of each token in the source, &source[span] is the text of the token
+ * 3. position - index of the next token, tokens before it have been consumed
+ */
+pub struct TokenStream {
+    tokens: Vec<(String, usize, usize)>,
+    spans: Vec<Range<usize>>,
+    position: usize,
+}
+
+impl Default for TokenStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenStream {
+    pub fn new() -> Self {
+        Self { tokens: Vec::new(), spans: Vec::new(), position: 0 }
+    }
+
+    // Append a token and its byte range at the end of the stream
+    pub fn push(&mut self, token: (String, usize, usize), span: Range<usize>) {
+        self.tokens.push(token);
+        self.spans.push(span);
+    }
+
+    // Return the next token without consuming it
+    pub fn peek(&self) -> Option<&(String, usize, usize)> {
+        self.tokens.get(self.position)
+    }
+
+    // Consume and return the next token
+    pub fn next_token(&mut self) -> Option<(String, usize, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    // Index of the next token
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // Line of the last token and the column right after it, where the input ends, line one for an empty stream
+    pub fn end_position(&self) -> (usize, usize) {
+        self.tokens
+            .last()
+            .map(|(token, line, column)| (*line, column + token.chars().count()))
+            .unwrap_or((1, 1))
+    }
+
+    // All the tokens in document order, consumed or not
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, usize, usize)> {
+        self.tokens.iter()
+    }
+
+    // All the tokens in document order with their line number and byte range, for tools that map a token back to the source
+    pub fn spanned(&self) -> Vec<(String, usize, Range<usize>)> {
+        self.tokens
+            .iter()
+            .zip(&self.spans)
+            .map(|((token, line, _column), span)| (token.clone(), *line, span.clone()))
+            .collect()
+    }
+}
+
 /**
  * Task 1 - Build a character by character lexical analyzer
  * LolcodeLexicalAnalyzer struct to define LexicalAnalyzer traits 
  * 1. input - vector for all characters extracted from the program string read from lolcode file
  * 2. position - line position for all tokens in the program in the string
  * 3. current_build - placeholder for building tokens through character-by-character reading in compilation
- * 4. tokens - vector holding tuples containing the extracted tokens from lolcode program, this vector will be used for lexical analysis and parsing later in the program
- * 5. line_number - integer value representing a specific line number inside a token
+ * 4. tokens - token stream holding tuples containing the extracted tokens from lolcode program in document order, this stream will be used for lexical analysis and parsing later in the program
+ * 5. line_number - integer value representing a specific line number inside a token, column_number and token_column the column of the last character read and the column where the current build started (both counted from one), byte_position and token_start the byte offset of the next character and the byte offset where the current build started
  * 6. head_start - vector to hold starting tag of the document - #hai
  * 7. head_end- vector to hold ending tag of the document - #kthxbye
  * 8. comment_start - vector to hold starting tag of comments - #obtw
@@ -124,6 +641,23 @@ pub trait LexicalAnalyzer {
  * 28. var_val - regex expression to enforce allowed variable values
  * 29. text - regex expression to declare acceptable text token
  * 30. address - regex compression to validate URL addresses
+ * 31. shorthand - compact aliases (e.g. #p, #/p) mapped to the keyword sequence they stand for, empty unless --shorthand is used
+ * 32. head1_element to head6_element - vectors to include the heading tags bighead, medhead, lilhead, smolhead, tinyhead and teenyhead - used to create h1 to h6 headings
+ * 33. link_element - vector to include the link tag - used to create hyperlinks
+ * 34. strict - report an unknown # token with the list of valid tags instead of the generic lexical error, false unless --strict is used
+ * 35. description_element - vector to include the description tag - used to create the description meta of the web page
+ * 36. styled_element - vector to include the styling tag - used to create text with a CSS class
+ * 37. stylesheet_element - vector to include the stylesheet tag - used to link CSS stylesheets in the head of the web page
+ * 38. table_element, row_element, cell_element - vectors to include the grid, roe and sel tags - used to create tables, their rows and their cells
+ * 39. hrule_element - vector to include the divider tag, similar to <hr> in html
+ * 40. quote_element - vector to include the quote tag - used to create block quotes
+ * 41. maths_element - vector to include the maths tag - used to emit the sum of two number variables
+ * 42. code_element - vector to include the code tag - used to create inline code that keeps its spacing
+ * 43. strongem_element - vector to include the shout tag - used to create text that is both bold and italics
+ * 44. image_element - vector to include the piccy tag - used to embed an image with its alt text
+ * 45. figure_element - vector to include the showcase tag - used to create a figure, an image with a caption
+ * 46. container_element - vector to include the boxz tag - used to group body content in a container with an optional .class
+ * 47. body_element - vector to include the body tag - used to group the body content of the web page explicitly, optional
  *
  * 
  * 
@@ -132,8 +666,12 @@ pub struct LolcodeLexicalAnalyzer {
     input: Vec<char>,
     position: usize,
     current_build: String,
-    tokens: Vec<(String, usize)>, // Token and line number
+    tokens: TokenStream, // Token, line number and column number
     line_number: usize,
+    column_number: usize,
+    token_column: usize,
+    byte_position: usize,
+    token_start: usize,
     head_start: Vec<String>,
     head_end: Vec<String>,
     comment_start: Vec<String>,
@@ -159,30 +697,133 @@ pub struct LolcodeLexicalAnalyzer {
     var_val: Regex,
     text: Regex,
     address: Regex,
+    shorthand: HashMap<String, Vec<String>>,
+    head1_element: Vec<String>,
+    head2_element: Vec<String>,
+    head3_element: Vec<String>,
+    head4_element: Vec<String>,
+    head5_element: Vec<String>,
+    head6_element: Vec<String>,
+    link_element: Vec<String>,
+    pub strict: bool,
+    description_element: Vec<String>,
+    styled_element: Vec<String>,
+    stylesheet_element: Vec<String>,
+    table_element: Vec<String>,
+    row_element: Vec<String>,
+    cell_element: Vec<String>,
+    hrule_element: Vec<String>,
+    quote_element: Vec<String>,
+    maths_element: Vec<String>,
+    code_element: Vec<String>,
+    strongem_element: Vec<String>,
+    image_element: Vec<String>,
+    figure_element: Vec<String>,
+    container_element: Vec<String>,
+    body_element: Vec<String>,
 }
 
-/***
- * Initialize elements for the impl LolcodeLexicalAnalyzer 
- * 1. input - initialized to hold characters from program string
- * 2. current_build - initialize new string builds
- * 3. tokens - initialize a new vector for created tokens
- * 4. line_number - intiailize line number from one
- * 5. Initialize vectors for all the lolcode compiler tags
- * 6. Regexes defined all four acceptable 
- * i. variable_definition - Any single word (A-Z, a-z, no spaces) - letters only
- * ii. variable_value - Allowed text characters - A-Z, a-xz, 0-9, commas, preiod, period, quotes, colons, question marks, underscores and forward slashes 
- * iii. text - allowed text in our language - A-Z, a-z, 0-9, commas, period, quotes, colons, question marks, underscores, and forward slashes
- * iv. address - allowed text characters without spaces
+// URL schemes allowed in addresses unless --allowed-schemes is given
+pub const DEFAULT_ALLOWED_SCHEMES: &[&str] = &["http", "https", "data"];
+
+// Deepest nesting of blocks and inline elements unless --max-depth is given
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+// Largest media file written into the html by --inline-media, a base64 data URI is a third larger than the file
+pub const INLINE_MEDIA_LIMIT: u64 = 1024 * 1024;
+
+// The byte range of the html of every block next to the source line of the block, built by to_html_with_map
+pub type SourceMap = Vec<(Range<usize>, usize)>;
+
+// Heading element keywords, from the h1 heading to the h6 heading
+pub const HEADING_ELEMENTS: &[&str] = &["bighead", "medhead", "lilhead", "smolhead", "tinyhead", "teenyhead"];
+
+/**
+ * Default compact keyword set used by --shorthand mode
+ * Each alias is written as one token and stands for the keyword sequence next to it, e.g. #p is #maek paragraf and #/p is #oic
  */
+pub const DEFAULT_SHORTHAND: &[(&str, &[&str])] = &[
+    ("#head", &["#maek", "head"]),
+    ("#/head", &["#oic"]),
+    ("#title", &["#gimmeh", "title"]),
+    ("#/title", &["#mkay"]),
+    ("#p", &["#maek", "paragraf"]),
+    ("#/p", &["#oic"]),
+    ("#ul", &["#maek", "list"]),
+    ("#/ul", &["#oic"]),
+    ("#li", &["#gimmeh", "item"]),
+    ("#/li", &["#mkay"]),
+    ("#b", &["#gimmeh", "bold"]),
+    ("#/b", &["#mkay"]),
+    ("#em", &["#gimmeh", "italics"]),
+    ("#/em", &["#mkay"]),
+    ("#br", &["#gimmeh", "newline"]),
+    ("#audio", &["#gimmeh", "soundz"]),
+    ("#/audio", &["#mkay"]),
+    ("#video", &["#gimmeh", "vidz"]),
+    ("#/video", &["#mkay"]),
+];
 
-impl LolcodeLexicalAnalyzer {
-    pub fn new(source: &str) -> Self {
+/**
+ * 1. #[derive(Clone)] - procedural macro to allow the keyword config to be copied into every lexer the compiler creates
+
+ */
+#[derive(Clone)]
+
+/**
+ * Keyword Config struct - the keywords of every tag of the language, passed to LolcodeLexicalAnalyzer::with_keywords
+ * Each field holds the keywords accepted for the tag of the lexer vector with the same name, e.g. head_start holds #hai
+ * The default reproduces the regular keywords, other dialects can rename them, e.g. #start instead of #hai
+ */
+pub struct KeywordConfig {
+    pub head_start: Vec<String>,
+    pub head_end: Vec<String>,
+    pub comment_start: Vec<String>,
+    pub comment_end: Vec<String>,
+    pub make_start: Vec<String>,
+    pub oic_end: Vec<String>,
+    pub gimmeh_start: Vec<String>,
+    pub mkay_end: Vec<String>,
+    pub variable_start: Vec<String>,
+    pub variable_mid: Vec<String>,
+    pub variable_end: Vec<String>,
+    pub head_element: Vec<String>,
+    pub title_element: Vec<String>,
+    pub paragraph_element: Vec<String>,
+    pub bold_element: Vec<String>,
+    pub italics_element: Vec<String>,
+    pub list_element: Vec<String>,
+    pub item_element: Vec<String>,
+    pub newline_element: Vec<String>,
+    pub soundz_element: Vec<String>,
+    pub vidz_element: Vec<String>,
+    pub head1_element: Vec<String>,
+    pub head2_element: Vec<String>,
+    pub head3_element: Vec<String>,
+    pub head4_element: Vec<String>,
+    pub head5_element: Vec<String>,
+    pub head6_element: Vec<String>,
+    pub link_element: Vec<String>,
+    pub description_element: Vec<String>,
+    pub styled_element: Vec<String>,
+    pub stylesheet_element: Vec<String>,
+    pub table_element: Vec<String>,
+    pub row_element: Vec<String>,
+    pub cell_element: Vec<String>,
+    pub hrule_element: Vec<String>,
+    pub quote_element: Vec<String>,
+    pub maths_element: Vec<String>,
+    pub code_element: Vec<String>,
+    pub strongem_element: Vec<String>,
+    pub image_element: Vec<String>,
+    pub figure_element: Vec<String>,
+    pub container_element: Vec<String>,
+    pub body_element: Vec<String>,
+}
+
+impl Default for KeywordConfig {
+    fn default() -> Self {
         Self {
-            input: source.chars().collect(),
-            position: 0,
-            current_build: String::new(),
-            tokens: Vec::new(),
-            line_number: 1,
             head_start: vec!["#hai".into()],
             head_end: vec!["#kthxbye".into()],
             comment_start: vec!["#obtw".into()],
@@ -204,44 +845,384 @@ impl LolcodeLexicalAnalyzer {
             newline_element: vec!["newline".into()],
             soundz_element: vec!["soundz".into()],
             vidz_element: vec!["vidz".into()],
+            head1_element: vec![HEADING_ELEMENTS[0].into()],
+            head2_element: vec![HEADING_ELEMENTS[1].into()],
+            head3_element: vec![HEADING_ELEMENTS[2].into()],
+            head4_element: vec![HEADING_ELEMENTS[3].into()],
+            head5_element: vec![HEADING_ELEMENTS[4].into()],
+            head6_element: vec![HEADING_ELEMENTS[5].into()],
+            link_element: vec!["linkz".into()],
+            description_element: vec!["describ".into()],
+            styled_element: vec!["stylez".into()],
+            stylesheet_element: vec!["dressup".into()],
+            table_element: vec!["grid".into()],
+            row_element: vec!["roe".into()],
+            cell_element: vec!["sel".into()],
+            hrule_element: vec!["divider".into()],
+            quote_element: vec!["saidz".into()],
+            maths_element: vec!["maths".into()],
+            code_element: vec!["codez".into()],
+            strongem_element: vec!["shout".into()],
+            image_element: vec!["piccy".into()],
+            figure_element: vec!["showcase".into()],
+            container_element: vec!["boxz".into()],
+            body_element: vec!["body".into()],
+        }
+    }
+}
+
+/***
+ * Initialize elements for the impl LolcodeLexicalAnalyzer 
+ * 1. input - initialized to hold characters from program string as is so the token spans index the source, the \r of CRLF line endings is whitespace so windows files count lines the same way
+ * 2. current_build - initialize new string builds
+ * 3. tokens - initialize a new token stream for created tokens
+ * 4. line_number - intiailize line number from one, column_number from zero so the first character is in column one
+ * 5. Initialize vectors for all the lolcode compiler tags from the keyword config, the default config holds the regular keywords
+ * 6. Regexes defined all four acceptable 
+ * i. variable_definition - Any single word (A-Z, a-z, no spaces) - letters only
+ * ii. variable_value - Allowed text characters - A-Z, a-xz, 0-9, commas, preiod, period, quotes, colons, question marks, underscores, forward slashes and <, >, &, " (escaped in the html)
+ * iii. text - allowed text in our language - A-Z, a-z, 0-9, commas, period, quotes, colons, question marks, underscores, forward slashes and <, >, &, " (escaped in the html)
+ * Both also accept any unicode letter, mark, digit or symbol (Café, ☕), control characters and # are still rejected
+ * iv. address - allowed text characters without spaces
+ */
+
+impl LolcodeLexicalAnalyzer {
+    pub fn new(source: &str) -> Self {
+        Self::with_keywords(source, KeywordConfig::default())
+    }
+
+    // Create a lexer that accepts the keywords of the given config instead of the regular ones
+    pub fn with_keywords(source: &str, keywords: KeywordConfig) -> Self {
+        Self {
+            input: source.chars().collect(),
+            position: 0,
+            current_build: String::new(),
+            tokens: TokenStream::new(),
+            line_number: 1,
+            column_number: 0,
+            token_column: 1,
+            byte_position: 0,
+            token_start: 0,
+            head_start: keywords.head_start,
+            head_end: keywords.head_end,
+            comment_start: keywords.comment_start,
+            comment_end: keywords.comment_end,
+            make_start: keywords.make_start,
+            oic_end: keywords.oic_end,
+            gimmeh_start: keywords.gimmeh_start,
+            mkay_end: keywords.mkay_end,
+            variable_start: keywords.variable_start,
+            variable_mid: keywords.variable_mid,
+            variable_end: keywords.variable_end,
+            head_element: keywords.head_element,
+            title_element: keywords.title_element,
+            paragraph_element: keywords.paragraph_element,
+            bold_element: keywords.bold_element,
+            italics_element: keywords.italics_element,
+            list_element: keywords.list_element,
+            item_element: keywords.item_element,
+            newline_element: keywords.newline_element,
+            soundz_element: keywords.soundz_element,
+            vidz_element: keywords.vidz_element,
             var_def: Regex::new(r"^[A-Za-z]+$").unwrap(),
-            var_val: Regex::new(r"^[A-Za-z0-9,\.\':\?!_\/ ]+$").unwrap(),
-            text: Regex::new(r"^[A-Za-z0-9,\.\':\?!_\/ ]+$").unwrap(),
+            var_val: Regex::new(r#"^[\p{L}\p{M}\p{N}\p{S},\.\':\?!_\/ <>&"]+$"#).unwrap(),
+            text: Regex::new(r#"^[\p{L}\p{M}\p{N}\p{S},\.\':\?!_\/ <>&"]+$"#).unwrap(),
             address: Regex::new(r"^[A-Za-z0-9,\.\':\?!_\/%]+$").unwrap(),
+            shorthand: HashMap::new(),
+            head1_element: keywords.head1_element,
+            head2_element: keywords.head2_element,
+            head3_element: keywords.head3_element,
+            head4_element: keywords.head4_element,
+            head5_element: keywords.head5_element,
+            head6_element: keywords.head6_element,
+            link_element: keywords.link_element,
+            strict: false,
+            description_element: keywords.description_element,
+            styled_element: keywords.styled_element,
+            stylesheet_element: keywords.stylesheet_element,
+            table_element: keywords.table_element,
+            row_element: keywords.row_element,
+            cell_element: keywords.cell_element,
+            hrule_element: keywords.hrule_element,
+            quote_element: keywords.quote_element,
+            maths_element: keywords.maths_element,
+            code_element: keywords.code_element,
+            strongem_element: keywords.strongem_element,
+            image_element: keywords.image_element,
+            figure_element: keywords.figure_element,
+            container_element: keywords.container_element,
+            body_element: keywords.body_element,
+        }
+    }
+
+    /***
+     * Function to enable a compact keyword set, each alias is added to the vector of the first keyword it stands for
+     * so the lexer and parser accept it as written, the remaining keywords are inserted after it while tokenizing
+     */
+    pub fn enable_shorthand(&mut self, aliases: &[(&str, &[&str])]) {
+        for (alias, keywords) in aliases {
+            let alias = alias.to_lowercase();
+
+            //Add the alias next to the keyword it replaces, skip aliases for unknown keywords
+            match keywords.first().and_then(|first| self.keyword_vector_mut(first)) {
+                Some(vector) => vector.push(alias.clone()),
+                None => continue,
+            }
+
+            self.shorthand
+                .insert(alias, keywords.iter().map(|keyword| keyword.to_string()).collect());
+        }
+    }
+
+    // function to find the vector of tags that contains a keyword
+    fn keyword_vector_mut(&mut self, keyword: &str) -> Option<&mut Vec<String>> {
+        let keyword = keyword.to_lowercase();
+        [
+            &mut self.head_start,
+            &mut self.head_end,
+            &mut self.comment_start,
+            &mut self.comment_end,
+            &mut self.make_start,
+            &mut self.oic_end,
+            &mut self.gimmeh_start,
+            &mut self.mkay_end,
+            &mut self.variable_start,
+            &mut self.variable_mid,
+            &mut self.variable_end,
+            &mut self.head_element,
+            &mut self.title_element,
+            &mut self.paragraph_element,
+            &mut self.bold_element,
+            &mut self.italics_element,
+            &mut self.list_element,
+            &mut self.item_element,
+            &mut self.newline_element,
+            &mut self.soundz_element,
+            &mut self.vidz_element,
+            &mut self.head1_element,
+            &mut self.head2_element,
+            &mut self.head3_element,
+            &mut self.head4_element,
+            &mut self.head5_element,
+            &mut self.head6_element,
+            &mut self.link_element,
+            &mut self.description_element,
+            &mut self.styled_element,
+            &mut self.stylesheet_element,
+            &mut self.table_element,
+            &mut self.row_element,
+            &mut self.cell_element,
+            &mut self.hrule_element,
+            &mut self.quote_element,
+            &mut self.maths_element,
+            &mut self.code_element,
+            &mut self.strongem_element,
+            &mut self.image_element,
+            &mut self.figure_element,
+            &mut self.container_element,
+            &mut self.body_element,
+        ]
+        .into_iter()
+        .find(|vector| vector.contains(&keyword))
+    }
+
+    // function to append the current build as a token, a shorthand alias is followed by the rest of the keywords it stands for
+    fn push_token(&mut self) {
+        let token = std::mem::take(&mut self.current_build);
+        let expansion = self.shorthand.get(&token.to_lowercase()).cloned();
+        let span = self.token_start..self.token_start + token.len();
+
+        self.tokens.push((token, self.line_number, self.token_column), span.clone());
+
+        // The alias itself takes the place of the first keyword, the keywords it stands for share its span
+        if let Some(keywords) = expansion {
+            for keyword in keywords.into_iter().skip(1) {
+                self.tokens.push((keyword, self.line_number, self.token_column), span.clone());
+            }
+        }
+    }
+
+    // Every keyword that starts with #, in the order of the tag vectors, shorthand aliases included
+    pub fn tag_keywords(&self) -> Vec<String> {
+        [
+            &self.head_start,
+            &self.head_end,
+            &self.comment_start,
+            &self.comment_end,
+            &self.make_start,
+            &self.oic_end,
+            &self.gimmeh_start,
+            &self.mkay_end,
+            &self.variable_start,
+            &self.variable_mid,
+            &self.variable_end,
+        ]
+        .iter()
+        .flat_map(|vector| vector.iter())
+        .filter(|keyword| keyword.starts_with("#"))
+        .cloned()
+        .collect()
+    }
+
+    // The tag closest to an unrecognized # token, only tags at most two edits away are suggested
+    // A short tag needs fewer edits than it has letters, otherwise #br would suggest #i
+    fn closest_tag(&self, token: &str) -> Option<String> {
+        if !is_tag(token) {
+            return None;
+        }
+        let lower = token.to_lowercase();
+        self.tag_keywords()
+            .into_iter()
+            .map(|tag| (edit_distance(&lower, &tag), tag))
+            .filter(|(distance, tag)| *distance <= 2 && *distance < tag.chars().count() - 1)
+            .min_by_key(|(distance, _tag)| *distance)
+            .map(|(_distance, tag)| tag)
+    }
+
+    // The heading element vectors in order, the vector of h1 first
+    fn heading_elements(&self) -> [&Vec<String>; 6] {
+        [
+            &self.head1_element,
+            &self.head2_element,
+            &self.head3_element,
+            &self.head4_element,
+            &self.head5_element,
+            &self.head6_element,
+        ]
+    }
+
+    // Public check that a single string is a valid token of the language, same rules as lookup
+    pub fn is_valid_token(&self, s: &str) -> bool {
+        self.lookup(s)
+    }
+
+    // Return the kind of token a string is, or None if it is not a valid token
+    pub fn classify_token(&self, s: &str) -> Option<TokenKind> {
+        let lower = s.to_lowercase();
+
+        //text that starts with an escaped literal #
+        if let Some(text) = s.strip_prefix("##") {
+            return (text.is_empty() || self.text.is_match(text)).then_some(TokenKind::Text);
+        }
+
+        //tags that start with hashtag markup notation
+        if s.starts_with("#") {
+            let tag_kinds = [
+                (&self.head_start, TokenKind::DocumentStart),
+                (&self.head_end, TokenKind::DocumentEnd),
+                (&self.comment_start, TokenKind::CommentStart),
+                (&self.comment_end, TokenKind::CommentEnd),
+                (&self.make_start, TokenKind::MakeStart),
+                (&self.oic_end, TokenKind::OicEnd),
+                (&self.gimmeh_start, TokenKind::GimmehStart),
+                (&self.mkay_end, TokenKind::MkayEnd),
+                (&self.variable_start, TokenKind::VariableStart),
+                (&self.variable_mid, TokenKind::VariableMid),
+                (&self.variable_end, TokenKind::VariableUse),
+            ];
+            return tag_kinds
+                .iter()
+                .find(|(tags, _kind)| tags.iter().any(|tag| tag == &lower))
+                .map(|(_tags, kind)| *kind);
+        }
+
+        //the second half of the two word variable keywords - haz, iz and see
+        if self.variable_start.contains(&lower) {
+            return Some(TokenKind::VariableStart);
+        }
+        if self.variable_mid.contains(&lower) {
+            return Some(TokenKind::VariableMid);
+        }
+        if self.variable_end.contains(&lower) {
+            return Some(TokenKind::VariableUse);
+        }
+
+        //element keywords
+        let elements = [
+            &self.head_element,
+            &self.title_element,
+            &self.paragraph_element,
+            &self.bold_element,
+            &self.italics_element,
+            &self.list_element,
+            &self.item_element,
+            &self.newline_element,
+            &self.soundz_element,
+            &self.vidz_element,
+            &self.link_element,
+            &self.description_element,
+            &self.styled_element,
+            &self.stylesheet_element,
+            &self.table_element,
+            &self.row_element,
+            &self.cell_element,
+            &self.hrule_element,
+            &self.quote_element,
+            &self.maths_element,
+            &self.code_element,
+            &self.strongem_element,
+            &self.image_element,
+            &self.figure_element,
+            &self.container_element,
+            &self.body_element,
+        ];
+        if elements.iter().chain(self.heading_elements().iter()).any(|element| element.contains(&lower)) {
+            return Some(TokenKind::Element);
+        }
+
+        //variable names, text and URL addresses
+        if self.var_def.is_match(s) {
+            Some(TokenKind::Identifier)
+        } else if self.text.is_match(s) || self.var_val.is_match(s) {
+            Some(TokenKind::Text)
+        } else if self.address.is_match(s) {
+            Some(TokenKind::Address)
+        } else {
+            None
         }
     }
 
+    // Return the tokens with shorthand aliases replaced by the keyword they stand for, used for HTML conversion
+    pub fn canonical_tokens(&self) -> Vec<(String, usize, usize)> {
+        self.tokens
+            .iter()
+            .map(|(token, line, column)| match self.shorthand.get(&token.to_lowercase()) {
+                Some(keywords) => (keywords[0].clone(), *line, *column),
+                None => (token.clone(), *line, *column),
+            })
+            .collect()
+    }
+
     /***
      * Function to build tokens from characters and append the tuples of tokens and line number to the tokens vector
      */
 
     pub fn tokenize(&mut self) {
         loop {
-            /// get character from program string 
+            // get character from program string 
             let c = self.get_char();
 
-            /// end of program string, break the loop
+            // end of program string, break the loop
             if c == '\0' {
                 break;
             }
 
-            /// If it reaches end of a line
+            // If it reaches end of a line
             if c == '\n'
             {
-                /// If the current build is not empty, append it as a  token with a line number in the form of tuple to the tokens vector
+                // If the current build is not empty, append it as a  token with a line number in the form of tuple to the tokens vector
                 if !self.current_build.is_empty() {
-                    self.tokens
-                        .push((std::mem::take(&mut self.current_build), self.line_number));
+                    self.push_token();
                 }
                 // Go to the next line of program string
                 self.line_number += 1;
+                self.column_number = 0;
             } 
 
             // If whitespace is found, if current_build is not empty, append it as a token with a line number in the form of tuple to the tokens vector
             else if c.is_whitespace() {
                 if !self.current_build.is_empty() {
-                    self.tokens
-                        .push((std::mem::take(&mut self.current_build), self.line_number));
+                    self.push_token();
                 }
             }
             // Else if there is a non-empty token, then add the character to the token
@@ -252,17 +1233,8 @@ impl LolcodeLexicalAnalyzer {
 
         // At the end, if the current_build is not empty, add the current_build as a tuple (current_build, line_number) to the tokens vector
         if !self.current_build.is_empty() {
-            self.tokens
-                .push((std::mem::take(&mut self.current_build), self.line_number));
+            self.push_token();
         }
-
-        // Reverse to get first token when popping from the tokens vector
-        self.tokens.reverse();
-    }
-
-    // Return the tokens for parsing variables for later html conversion if parsing is syntactically valid
-    pub fn return_tokens(&mut self) {
-        self.tokens.clone();
     }
 
     // function to match variable token names based on variable definition rules
@@ -282,8 +1254,10 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
         // get the value based on an index from the input vector
         let c = self.input[self.position];
 
-        // increment the position
+        // increment the position, the column and the byte offset
         self.position += 1;
+        self.column_number += 1;
+        self.byte_position += c.len_utf8();
 
         // return the value
         c
@@ -295,6 +1269,12 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
         // get the character from the input vector
         let c = self.input[self.position - 1];
 
+        // remember the column and the byte offset of the first character of the token
+        if self.current_build.is_empty() {
+            self.token_column = self.column_number;
+            self.token_start = self.byte_position - c.len_utf8();
+        }
+
         // append the character to the current build to form a token
         self.current_build.push(c);
 
@@ -306,19 +1286,14 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
     // return false if a token does not match any of these lexeme rules
     fn lookup(&self, s: &str) -> bool {
 
+        //check text that starts with ##, it is written with a literal # and is not a tag
+        if let Some(text) = s.strip_prefix("##") {
+            return text.is_empty() || self.text.is_match(text);
+        }
+
         //check tags that start with hashtag markup notation
         if s.starts_with("#") {
-            return self.head_start.iter().any(|h| h == &s.to_lowercase())
-                || self.head_end.iter().any(|h| h == &s.to_lowercase())
-                || self.comment_start.iter().any(|h| h == &s.to_lowercase())
-                || self.comment_end.iter().any(|h| h == &s.to_lowercase())
-                || self.make_start.iter().any(|h| h == &s.to_lowercase())
-                || self.oic_end.iter().any(|h| h == &s.to_lowercase())
-                || self.gimmeh_start.iter().any(|h| h == &s.to_lowercase())
-                || self.mkay_end.iter().any(|h| h == &s.to_lowercase())
-                || self.variable_start.iter().any(|h| h == &s.to_lowercase())
-                || self.variable_mid.iter().any(|h| h == &s.to_lowercase())
-                || self.variable_end.iter().any(|h| h == &s.to_lowercase());
+            return self.tag_keywords().contains(&s.to_lowercase());
         }
 
         //check other non element hashtags, and other acceptable text, URL address, variable definition, and variable value formats
@@ -335,6 +1310,26 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
             || self.newline_element.iter().any(|h| h == &s.to_lowercase())
             || self.soundz_element.iter().any(|h| h == &s.to_lowercase())
             || self.vidz_element.iter().any(|h| h == &s.to_lowercase())
+            || self.link_element.iter().any(|h| h == &s.to_lowercase())
+            || self.description_element.iter().any(|h| h == &s.to_lowercase())
+            || self.styled_element.iter().any(|h| h == &s.to_lowercase())
+            || self.stylesheet_element.iter().any(|h| h == &s.to_lowercase())
+            || self.table_element.iter().any(|h| h == &s.to_lowercase())
+            || self.row_element.iter().any(|h| h == &s.to_lowercase())
+            || self.cell_element.iter().any(|h| h == &s.to_lowercase())
+            || self.hrule_element.iter().any(|h| h == &s.to_lowercase())
+            || self.quote_element.iter().any(|h| h == &s.to_lowercase())
+            || self.maths_element.iter().any(|h| h == &s.to_lowercase())
+            || self.code_element.iter().any(|h| h == &s.to_lowercase())
+            || self.strongem_element.iter().any(|h| h == &s.to_lowercase())
+            || self.image_element.iter().any(|h| h == &s.to_lowercase())
+            || self.figure_element.iter().any(|h| h == &s.to_lowercase())
+            || self.container_element.iter().any(|h| h == &s.to_lowercase())
+            || self.body_element.iter().any(|h| h == &s.to_lowercase())
+            || self
+                .heading_elements()
+                .iter()
+                .any(|heading| heading.iter().any(|h| h == &s.to_lowercase()))
             || self.text.is_match(s)
             || self.address.is_match(s)
             || self.var_def.is_match(s)
@@ -348,6 +1343,8 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
  * Task 2 - Build a recursive descent parser 
  * Trait required to implement as given by project guidelines
  * Methods: 
+ * Each method returns the HtmlNode tree of what it parsed, variable definitions only update the scope stack
+ * A syntax error is returned to parse_lolcode or parse_body, which record it and continue from the next body element
  * 1. parse_lolcode - parse the structure of lolcode other than #HAI and #KTHXBYE tags
  * 2. parse_head - parse the head portion of the page
  * 3. parse_title - parse the title inside the head portion of the web page
@@ -370,45 +1367,93 @@ impl LexicalAnalyzer for LolcodeLexicalAnalyzer {
  * 20. parse_inner_text - parse the inner text of the lolcode script
  * 21. parse_variable_define - parse the variable definition of the lolcode script
  * 22. parse_variable_use - parse the variable usage of the lolcode script
+ * 23. parse_heading - parse the heading portion of the web page
+ * 24. parse_link - parse the link tags of the lolcode script
+ * 25. parse_description - parse the description of the head, at most one is allowed
+ * 26. parse_styled - parse the styling tags of the lolcode script
+ * 27. parse_stylesheet - parse the stylesheet links of the head
+ * 28. parse_table - parse the table portion of the web page
+ * 29. parse_row - parse the rows inside a table
+ * 30. parse_cell - parse the cells inside a table row
+ * 31. parse_hrule - parse the divider tags of the lolcode script
+ * 32. parse_quote - parse the quote portion of the web page
+ * 33. parse_maths - parse the maths tags of the lolcode script
+ * 34. parse_code - parse the code tags of the lolcode script
+ * 35. parse_shout - parse the shout tags of the lolcode script
+ * 36. parse_image - parse the piccy tags of the lolcode script
+ * 37. parse_figure - parse the showcase tags of the lolcode script
+ * 38. parse_container - parse the boxz tags of the lolcode script
+ * 39. parse_body_block - parse the optional body tags of the lolcode script
  */
 pub trait SyntaxAnalyzer {
-    fn parse_lolcode(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_head(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_title(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_comments(&mut self, compiler: &mut LolcodeCompiler); 
-    fn parse_comment(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_body(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_inner_body(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_paragraph(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_inner_paragraph(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_list(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_list_items(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_inner_list(&mut self, compiler: &mut LolcodeCompiler);
-
-    fn parse_item(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_audio(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_video(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_newline(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_bold(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_italics(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_text(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_inner_text(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_variable_define(&mut self, compiler: &mut LolcodeCompiler);
-    fn parse_variable_use(&mut self, compiler: &mut LolcodeCompiler);
-}
-
-// Struct definition of parser, containing current_line to represent the line of a given token
+    fn parse_lolcode(&mut self, compiler: &mut LolcodeCompiler) -> HtmlNode;
+    fn parse_head(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_title(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_comments(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError>;
+    fn parse_comment(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_body(&mut self, compiler: &mut LolcodeCompiler) -> Vec<HtmlNode>;
+    fn parse_inner_body(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError>;
+    fn parse_paragraph(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_inner_paragraph(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError>;
+    fn parse_list(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_list_items(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError>;
+    fn parse_inner_list(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError>;
+
+    fn parse_item(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_audio(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_video(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_newline(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_bold(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_italics(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_text(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError>;
+    fn parse_inner_text(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError>;
+    fn parse_variable_define(&mut self, compiler: &mut LolcodeCompiler) -> Result<(), CompileError>;
+    fn parse_variable_use(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_heading(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_link(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_description(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_styled(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_stylesheet(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_table(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_row(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_cell(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_hrule(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_quote(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_maths(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_code(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_shout(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_image(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_figure(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_container(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+    fn parse_body_block(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError>;
+}
+
+// Parser of an element that follows #gimmeh, called with the tag of the element as the current token
+type GimmehParser = fn(&mut LolcodeSyntaxAnalyzer, &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>;
+
+// Entry of a #gimmeh dispatch table, the keywords of an element and the parser of the element
+type GimmehEntry<'a> = (&'a Vec<String>, GimmehParser);
+
+// Struct definition of parser, the position of the current token is kept on the compiler because the parser is swapped out of the compiler while it parses
+// The parser keeps the depth of the blocks and inline elements it is inside, and where the last newline element ended to find repeated newlines
 pub struct LolcodeSyntaxAnalyzer {
-    current_line: usize,
+    depth: usize,
+    newline_end: Option<usize>,
+}
+
+impl Default for LolcodeSyntaxAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Implementation for lolcode syntax analyzer methods, contains utility method 
 impl LolcodeSyntaxAnalyzer {
     pub fn new() -> Self {
-        Self { current_line: 1 }
+        Self { depth: 0, newline_end: None }
     }
 
-    /// Helper methods to check token types using compiler's lexer elements which contain the allowed lexemes
+    // Helper methods to check token types using compiler's lexer elements which contain the allowed lexemes
     
 
     /// check if the token at the end of element is #kthxbye
@@ -553,13 +1598,114 @@ impl LolcodeSyntaxAnalyzer {
             .any(|vid| vid == &s.to_lowercase())
     }
 
-    /// check if the token entered matches accepted tokens allowed in text of the language 
-    fn is_text(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
-        lexer.text.is_match(s)
+    /// check if the token entered represents link element - linkz
+    fn is_link_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer
+            .link_element
+            .iter()
+            .any(|link| link == &s.to_lowercase())
     }
 
-    /// check if the token entered matches web URLS found in audio or video hyperlinks 
-    fn is_address(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+    /// check if the token entered represents description element - describ
+    fn is_description_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer
+            .description_element
+            .iter()
+            .any(|description| description == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents styling element - stylez
+    fn is_styled_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer
+            .styled_element
+            .iter()
+            .any(|styled| styled == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents stylesheet element - dressup
+    fn is_stylesheet_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer
+            .stylesheet_element
+            .iter()
+            .any(|stylesheet| stylesheet == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents table element - grid
+    fn is_table_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.table_element.iter().any(|table| table == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents row element - roe
+    fn is_row_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.row_element.iter().any(|row| row == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents cell element - sel
+    fn is_cell_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.cell_element.iter().any(|cell| cell == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents hrule element - divider
+    fn is_hrule_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.hrule_element.iter().any(|hrule| hrule == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents quote element - saidz
+    fn is_quote_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.quote_element.iter().any(|quote| quote == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents maths element - maths
+    fn is_maths_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.maths_element.iter().any(|maths| maths == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents code element - codez
+    fn is_code_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.code_element.iter().any(|code| code == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents shout element - shout
+    fn is_shout_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.strongem_element.iter().any(|shout| shout == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents image element - piccy
+    fn is_image_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.image_element.iter().any(|image| image == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents figure element - showcase
+    fn is_figure_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.figure_element.iter().any(|figure| figure == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents container element - boxz
+    fn is_container_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.container_element.iter().any(|container| container == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents body element - body
+    fn is_body_element(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.body_element.iter().any(|body| body == &s.to_lowercase())
+    }
+
+    /// check if the token entered represents a heading element - bighead to teenyhead, returns the heading level 1 to 6
+    fn heading_level(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> Option<usize> {
+        lexer
+            .heading_elements()
+            .iter()
+            .position(|heading| heading.iter().any(|h| h == &s.to_lowercase()))
+            .map(|index| index + 1)
+    }
+
+    /// check if the token entered matches accepted tokens allowed in text of the language 
+    fn is_text(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        lexer.text.is_match(s)
+    }
+
+    /// check if the token entered matches web URLS found in audio or video hyperlinks 
+    fn is_address(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
         lexer.address.is_match(s)
     }
 
@@ -568,50 +1714,231 @@ impl LolcodeSyntaxAnalyzer {
         lexer.is_variable_identifier(s)
     }
 
+    // Elements accepted after #gimmeh in the body, with their parsers, in the order they are listed in an error
+    fn body_gimmeh_elements(lexer: &LolcodeLexicalAnalyzer) -> Vec<GimmehEntry<'_>> {
+        vec![
+            (&lexer.bold_element, Self::parse_bold),
+            (&lexer.italics_element, Self::parse_italics),
+            (&lexer.strongem_element, Self::parse_shout),
+            (&lexer.soundz_element, Self::parse_audio),
+            (&lexer.vidz_element, Self::parse_video),
+            (&lexer.newline_element, Self::parse_newline),
+            (&lexer.hrule_element, Self::parse_hrule),
+            (&lexer.link_element, Self::parse_link),
+            (&lexer.styled_element, Self::parse_styled),
+            (&lexer.maths_element, Self::parse_maths),
+            (&lexer.code_element, Self::parse_code),
+            (&lexer.image_element, Self::parse_image),
+        ]
+    }
+
+    // Elements accepted after #gimmeh in paragraph and quote contents
+    fn text_gimmeh_elements(lexer: &LolcodeLexicalAnalyzer) -> Vec<GimmehEntry<'_>> {
+        vec![
+            (&lexer.bold_element, Self::parse_bold),
+            (&lexer.italics_element, Self::parse_italics),
+            (&lexer.strongem_element, Self::parse_shout),
+            (&lexer.newline_element, Self::parse_newline),
+            (&lexer.hrule_element, Self::parse_hrule),
+            (&lexer.soundz_element, Self::parse_audio),
+            (&lexer.vidz_element, Self::parse_video),
+            (&lexer.link_element, Self::parse_link),
+            (&lexer.styled_element, Self::parse_styled),
+            (&lexer.maths_element, Self::parse_maths),
+            (&lexer.code_element, Self::parse_code),
+            (&lexer.image_element, Self::parse_image),
+        ]
+    }
+
+    // Elements accepted after #gimmeh inside bold, italics, stylez, list items and table cells
+    fn inline_gimmeh_elements(lexer: &LolcodeLexicalAnalyzer) -> Vec<GimmehEntry<'_>> {
+        vec![
+            (&lexer.bold_element, Self::parse_bold),
+            (&lexer.italics_element, Self::parse_italics),
+            (&lexer.strongem_element, Self::parse_shout),
+            (&lexer.newline_element, Self::parse_newline),
+            (&lexer.link_element, Self::parse_link),
+            (&lexer.styled_element, Self::parse_styled),
+            (&lexer.maths_element, Self::parse_maths),
+            (&lexer.code_element, Self::parse_code),
+            (&lexer.image_element, Self::parse_image),
+        ]
+    }
+
+    // Parse the element after #gimmeh with the parser of its tag, report the first keyword of every accepted element if the tag is not one of them
+    // The expected list comes from the same table as the parsers, so a new element shows up in the error once it is in the table
+    fn parse_gimmeh_element(
+        &mut self,
+        compiler: &mut LolcodeCompiler,
+        elements: fn(&LolcodeLexicalAnalyzer) -> Vec<GimmehEntry<'_>>,
+    ) -> Result<HtmlNode, CompileError> {
+        let tag = compiler.current_tok.to_lowercase();
+        let table = elements(&compiler.lexer);
+
+        match table.iter().find(|(keywords, _parser)| keywords.contains(&tag)) {
+            Some((_keywords, parser)) => {
+                let parser = *parser;
+                parser(self, compiler)
+            }
+            None => {
+                let expected: Vec<&str> = table
+                    .iter()
+                    .filter_map(|(keywords, _parser)| keywords.first().map(|keyword| keyword.as_str()))
+                    .collect();
+                Err(syntax_error(compiler.token_line(), compiler.token_column(), &expected, &compiler.current_tok))
+            }
+        }
+    }
+
+    // Parse the contents of a bold, italics or list item element till its #mkay, which is left as the current token
+    // Bold, italics, newline, linkz and stylez elements can be nested, each nested element closes with its own #mkay
+    fn parse_inline_contents(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError> {
+        let mut children = Vec::new();
+
+        while !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            //parse variable usage if it is found
+            if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
+                children.push(self.parse_variable_use(compiler)?);
+            }
+
+            //a nested inline element, #gimmeh is consumed here as in parse_inner_text
+            else if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+                compiler.current_tok = compiler.next_token();
+
+                children.push(self.nested(compiler, |parser, compiler| parser.parse_gimmeh_element(compiler, Self::inline_gimmeh_elements))?);
+            }
+
+            //parse text if no tags are found, report an error for anything else
+            else {
+                match self.parse_text(compiler)? {
+                    Some(text) => children.push(text),
+                    None => {
+                        return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text", "#lemme", "#gimmeh", "#mkay"], &compiler.current_tok));
+                    }
+                }
+            }
+        }
+
+        Ok(children)
+    }
+
+    /// parse a block or inline element one level deeper, report an error instead of nesting past the maximum depth
+    fn nested(
+        &mut self,
+        compiler: &mut LolcodeCompiler,
+        parse: impl FnOnce(&mut Self, &mut LolcodeCompiler) -> Result<HtmlNode, CompileError>,
+    ) -> Result<HtmlNode, CompileError> {
+        if self.depth >= compiler.max_depth {
+            return Err(CompileError::Structure {
+                line: compiler.token_line(),
+                message: format!("Maximum nesting depth exceeded, elements can be nested at most {} deep.", compiler.max_depth),
+            });
+        }
+
+        //The element keyword as written and its line, for the notes of --pedantic
+        let keyword = compiler.current_tok.clone();
+        let line = compiler.token_line();
+
+        self.depth += 1;
+        let result = parse(self, compiler);
+        self.depth -= 1;
+        let result = result?;
+
+        //Note the blocks and elements closed right after they were opened
+        if is_empty_element(&result) {
+            let kind = if is_block(&result) { "block" } else { "element" };
+            compiler.pedantic_notes.push((line, format!("empty '{}' {}.", keyword, kind)));
+        }
+
+        //Note a newline element that starts where the previous one ended, its #gimmeh is two tokens before the current token
+        if matches!(result, HtmlNode::Newline) {
+            if self.newline_end.is_some() && self.newline_end == compiler.lexer.tokens.position().checked_sub(3) {
+                compiler.pedantic_notes.push((line, "multiple consecutive newlines.".to_string()));
+            }
+            self.newline_end = compiler.lexer.tokens.position().checked_sub(1);
+        }
+
+        Ok(result)
+    }
+
+    /// record a syntax error and skip tokens till a tag that can start a body element, or the end of the document
+    fn recover(&mut self, compiler: &mut LolcodeCompiler, error: CompileError) {
+        compiler.errors.push(error);
+
+        // Variables of a paragraph the error was found in go out of scope
+        compiler.scope_stack.truncate(1);
+
+        // Skip the token the error was found at unless it ends the document (an unclosed comment stops there), then everything till the next body element
+        if !self.is_document_end(&compiler.current_tok, &compiler.lexer) {
+            compiler.current_tok = compiler.next_token();
+        }
+        while !compiler.current_tok.is_empty() && !self.is_recovery_point(&compiler.current_tok, &compiler.lexer) {
+            compiler.current_tok = compiler.next_token();
+        }
+    }
+
+    /// check if the token ends the document or can start a body element, where parsing continues after an error
+    fn is_recovery_point(&self, s: &str, lexer: &LolcodeLexicalAnalyzer) -> bool {
+        self.is_document_end(s, lexer)
+            || self.is_make_start(s, lexer)
+            || self.is_gimmeh_start(s, lexer)
+            || self.is_comment_start(s, lexer)
+            || (is_tag(s) && (self.is_variable_start(s, lexer) || self.is_variable_end(s, lexer)))
+    }
+
   
 }
 
 impl SyntaxAnalyzer for LolcodeSyntaxAnalyzer {
-    // Parse the #HAI tag at the start of document, report a syntax error if it is missing
-    fn parse_lolcode(&mut self, compiler: &mut LolcodeCompiler) {
-        
+    // Parse the document between #HAI and #KTHXBYE into a document node, record a syntax error if a part is missing and continue with the body
+    fn parse_lolcode(&mut self, compiler: &mut LolcodeCompiler) -> HtmlNode {
+
+        // nodes of the document in order
+        let mut nodes = Vec::new();
+
         //Parse comments if any comments are found
-            self.parse_comments(compiler);
+        match self.parse_comments(compiler) {
+            Ok(comments) => nodes.extend(comments),
+            Err(error) => self.recover(compiler, error),
+        }
 
 
         // Allow variable declarations before head
         while self.is_variable_start(&compiler.current_tok, &compiler.lexer) {
-                 self.parse_variable_define(compiler);
+            if let Err(error) = self.parse_variable_define(compiler) {
+                self.recover(compiler, error);
+            }
         }
 
         // Parse head elements if any head elements are found
-        self.parse_head(compiler);
+        match self.parse_head(compiler) {
+            Ok(head) => nodes.push(head),
+            Err(error) => self.recover(compiler, error),
+        }
 
         // Parse body elements if any body elements are found
-        self.parse_body(compiler);
-        
+        nodes.extend(self.parse_body(compiler));
 
+        HtmlNode::Document(nodes)
 }
 
     // Parse comments by going through each individual comment as described in BNF grammar
-    fn parse_comments(&mut self, compiler: &mut LolcodeCompiler) {
+    fn parse_comments(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError> {
+        let mut comments = Vec::new();
         while self.is_comment_start(&compiler.current_tok, &compiler.lexer)
         {
-            self.parse_comment(compiler);
+            comments.push(self.parse_comment(compiler)?);
         }
 
+        Ok(comments)
     }
 
-    // Parse head element by going through components of the head element - requires a #maek tag, head element, title element, and oic
-    fn parse_head(&mut self, compiler: &mut LolcodeCompiler) {
+    // Parse head element by going through components of the head element - requires a #maek tag, head element and oic, the title element is optional
+    fn parse_head(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
         // Expect #MAEK, if #MAEK not found report a syntax error
         if !self.is_make_start(&compiler.current_tok, &compiler.lexer){
-            eprintln!(
-                "Syntax error at line {}: Expected '#maek', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#maek"], &compiler.current_tok));
         }
 
         //get the next token from the compiler
@@ -619,45 +1946,71 @@ impl SyntaxAnalyzer for LolcodeSyntaxAnalyzer {
 
         // Expect HEAD, if HEAD not found report a syntax error
         if !self.is_head_element(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected 'head', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["head"], &compiler.current_tok));
         }
 
+        // Remember the line of the head element for source comments
+        let line = compiler.token_line();
+
         //get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-        // Parse title - described later in the code
-        self.parse_title(compiler);
+        // Parse an optional title, an optional description and any number of stylesheets in any order - described later in the code
+        // Without a title the html gets the text of the first heading or the --default-title
+        let mut children = Vec::new();
+        let mut title_line: Option<usize> = None;
+        let mut description_line: Option<usize> = None;
+        loop {
+            if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
+                && self.is_description_element(&compiler.peek_token(), &compiler.lexer)
+            {
+                // Report a second description with the line of the first one
+                if let Some(first_line) = description_line {
+                    return Err(CompileError::Structure {
+                        line: compiler.token_line(),
+                        message: format!("only one describ is allowed in the head; the first one is at line {}.", first_line),
+                    });
+                }
+                description_line = Some(compiler.token_line());
+                children.push(self.parse_description(compiler)?);
+            } else if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
+                && self.is_stylesheet_element(&compiler.peek_token(), &compiler.lexer)
+            {
+                children.push(self.parse_stylesheet(compiler)?);
+            } else if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
+                && self.is_title_element(&compiler.peek_token(), &compiler.lexer)
+            {
+                // Report a second title with the line of the first one
+                if let Some(first_line) = title_line {
+                    return Err(CompileError::Structure {
+                        line: compiler.token_line(),
+                        message: format!("Head may contain only one title; the first one is at line {}.", first_line),
+                    });
+                }
+                title_line = Some(compiler.token_line());
+                children.push(self.parse_title(compiler)?);
+            } else {
+                break;
+            }
+        }
 
         // Expect #OIC, if #oic not found report a syntax error
         if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#oic', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#oic"], &compiler.current_tok));
         }
 
         //get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-
-        //If head not found, skip this function
+        Ok(HtmlNode::Head { line, children })
     }
 
     //Parse title based on its definition given in BNF, needs #gimmeh, title tag, title text and mkay tag
-    fn parse_title(&mut self, compiler: &mut LolcodeCompiler) {
+    fn parse_title(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
         // Expect #GIMMEH, if #gimmeh is not found - report an error
         if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#gimmeh', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
         }
 
         //get next token from the compiler
@@ -665,324 +2018,366 @@ impl SyntaxAnalyzer for LolcodeSyntaxAnalyzer {
 
         // Expect TITLE, if title is not found - report an error
         if !self.is_title_element(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected 'title', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["title"], &compiler.current_tok));
         }
 
+        // Remember the line of the title element, reported if the input ends inside the title
+        let line = compiler.token_line();
+
         //get next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-        // Consume text until #MKAY tag is found using parse text method, report an error if token is found empty
+        // Consume text and variable usage until #MKAY tag is found, report an error if token is found empty
+        let mut children = Vec::new();
         while !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
             if compiler.current_tok.is_empty() {
-                eprintln!(
-                    "Syntax error at line {}: Unexpected end of input in title.",
-                    self.current_line
-                );
-                std::process::exit(1);
+                return Err(CompileError::Unterminated { line, block: "title".to_string() });
+            }
+
+            //parse variable usage if found, the value is resolved from the variables declared before the head
+            if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
+                children.push(self.parse_variable_use(compiler).map_err(|error| unterminated(error, "title", line))?);
+                continue;
+            }
+
+            //a comment is left out, the title is written as plain text, a title with only a comment is an empty <title></title>
+            if self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
+                self.parse_comment(compiler).map_err(|error| unterminated(error, "title", line))?;
+                continue;
+            }
+
+            //consumre text tokens, a tag other than #lemme and #mkay cannot be part of the title
+            match self.parse_text(compiler).map_err(|error| unterminated(error, "title", line))? {
+                Some(text) => children.push(text),
+                None => return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text", "#lemme", "#mkay"], &compiler.current_tok)),
             }
-            
-            //consumre text tokens
-            self.parse_text(compiler);
         }
 
         // Consume #MKAY at the end
         compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Title(children))
     }
 
     // parse individual comments - look for #obtw text #tldr
-    fn parse_comment(&mut self, compiler: &mut LolcodeCompiler) {
+    fn parse_comment(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
 
-    
     // Expect #obtw if not found - report an error
     if !self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
-        eprintln!(
-            "Syntax error at line {}: Expected comment start '#obtw', found '{}'.",
-            self.current_line, compiler.current_tok
-        );
-        std::process::exit(1);
+        return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#obtw"], &compiler.current_tok));
     }
-    
+
     // get the next token from the compiler
     compiler.current_tok = compiler.next_token();
-    
-    // get the text tokens from the compiler
-    self.parse_text(compiler);
-    
-    // Expect #tldr at the end of comment, if not found - report an error
-    if !self.is_comment_end(&compiler.current_tok, &compiler.lexer) {
-        eprintln!(
-            "Syntax error at line {}: Expected comment end '#tldr', found '{}'.",
-            self.current_line, compiler.current_tok
-        );
-        std::process::exit(1);
+
+    // Comments nest, an inner #obtw opens a level and #tldr closes one, the inner tags are kept in the comment text
+    let mut words: Vec<String> = Vec::new();
+    let mut depth = 1;
+    loop {
+        // get the text tokens from the compiler
+        if let Some(HtmlNode::Text(text)) = self.parse_text(compiler)? {
+            words.push(text);
+        }
+
+        if self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
+            depth += 1;
+        } else if self.is_comment_end(&compiler.current_tok, &compiler.lexer) {
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        }
+        // Expect #tldr at the end of comment, if not found - report an error
+        else {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#tldr"], &compiler.current_tok));
+        }
+
+        words.push(compiler.current_tok.clone());
+        compiler.current_tok = compiler.next_token();
     }
-    
+
     // get the next token from the compiler
     compiler.current_tok = compiler.next_token();
- 
+
+    Ok(HtmlNode::Comment(words.join(" ")))
 }
 
 // parse the body of the lolcode script till the #kthxbye tag as given in BNF
-    fn parse_body(&mut self, compiler: &mut LolcodeCompiler) {
-        // Parse body elements until we hit #KTHXBYE
-        if !self.is_document_end(&compiler.current_tok, &compiler.lexer) 
+// The body content can be grouped in an explicit #maek body ... #oic block, it then holds all of the body content, comments can come around it
+    fn parse_body(&mut self, compiler: &mut LolcodeCompiler) -> Vec<HtmlNode> {
+        let mut nodes = Vec::new();
+        let mut explicit_body = false;
+
+        // Parse body elements until we hit #KTHXBYE or the input runs out, an empty body is acceptable
+        // A loop instead of a call per element, a long document does not grow the stack
+        while !self.is_document_end(&compiler.current_tok, &compiler.lexer) && !compiler.current_tok.is_empty()
         {
-            //parse the inner body
-            self.parse_inner_body(compiler); 
-
-            //recursive call to the function - if it is empty, it is acceptable
-            self.parse_body(compiler);
+            let line = compiler.token_line();
+
+            // The content of a body block is the same as the content of an implicit body, a single body is written either way
+            if self.is_make_start(&compiler.current_tok, &compiler.lexer) && self.is_body_element(&compiler.peek_token(), &compiler.lexer) {
+                if explicit_body || nodes.iter().any(|node| !matches!(node, HtmlNode::Comment(_))) {
+                    compiler.errors.push(CompileError::Structure {
+                        line,
+                        message: "A body block must hold all of the body content.".to_string(),
+                    });
+                }
+                match self.parse_body_block(compiler) {
+                    Ok(children) => {
+                        nodes.extend(children);
+                        explicit_body = true;
+                    }
+                    Err(error) => self.recover(compiler, error),
+                }
+                continue;
+            }
 
+            //parse the inner body, record an error and continue from the next body element
+            match self.parse_inner_body(compiler) {
+                Ok(node) => {
+                    if explicit_body && node.as_ref().is_some_and(|node| !matches!(node, HtmlNode::Comment(_))) {
+                        compiler.errors.push(CompileError::Structure {
+                            line,
+                            message: "A body block must hold all of the body content.".to_string(),
+                        });
+                    }
+                    nodes.extend(node)
+                }
+                Err(error) => self.recover(compiler, error),
+            }
         }
-            
+
+        nodes
     }
 
 // parse the inner body defined in the parse_body, contains variable definition, paragraf, list, bold, italicz, sound, video, newline elements, variable usage, comments, and text
-   fn parse_inner_body(&mut self, compiler: &mut LolcodeCompiler) {
- 
+   fn parse_inner_body(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError> {
+
     // Don't call next_token here - we already have the current token from parse_body
-    
-    // If a variable is defined, parse it here
+
+    // If a variable is defined, parse it here, a definition has no node of its own
     if self.is_variable_start(&compiler.current_tok, &compiler.lexer) {
-        return;
-    }        
+        self.parse_variable_define(compiler)?;
+        Ok(None)
+    }
     // else if the token found is  #maek tag, it can be either a paragraf or a list
     else if self.is_make_start(&compiler.current_tok, &compiler.lexer) {
         // Consume #MAEK and get the block type
         compiler.current_tok = compiler.next_token();
-        
-       
+
+
         // If it is a paragraf tag, parse it as a paragraf
         if self.is_paragraph_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_paragraph(compiler);
+            Ok(Some(self.nested(compiler, Self::parse_paragraph)?))
         }
 
         // If it is a paragraf tag, parse it as a list
         else if self.is_list_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_list(compiler);
+            Ok(Some(self.nested(compiler, Self::parse_list)?))
         }
 
-        // Report an error if #maek is found and there is neither paragraf nor list
-        else {
-            eprintln!(
-                "Syntax error at line {}: Expected 'paragraf' or 'list', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+        // If it is a heading tag, parse it as a heading
+        else if self.heading_level(&compiler.current_tok, &compiler.lexer).is_some() {
+            Ok(Some(self.nested(compiler, Self::parse_heading)?))
         }
-        return; 
-    }
-    // If the next token found is #gimmeh, 
-    else if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
 
-        //get the next token to determine which tag it its
-        compiler.current_tok = compiler.next_token(); 
-
-        // if it is a bold element, parse it using the bold element, here #gimmeh and bold are checked before sending it to parse_bold
-        if self.is_bold_element(&compiler.current_tok, &compiler.lexer) {
-            compiler.current_tok = compiler.next_token(); 
-            self.parse_bold(compiler);
-            return;
-        } 
-
-        // if it is an italics element, parse it using the italics element, here #gimmeh and italics are checked before sending it to parse_italics
-        else if self.is_italics_element(&compiler.current_tok, &compiler.lexer) {
-            compiler.current_tok = compiler.next_token(); 
-            self.parse_italics(compiler);
-            return;
+        // If it is a grid tag, parse it as a table
+        else if self.is_table_element(&compiler.current_tok, &compiler.lexer) {
+            Ok(Some(self.nested(compiler, Self::parse_table)?))
         }
 
-        //if it is a soundz element, parse it using the sound element, and return back 
-        else if self.is_soundz_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_audio(compiler);
-            return;
+        // If it is a saidz tag, parse it as a quote
+        else if self.is_quote_element(&compiler.current_tok, &compiler.lexer) {
+            Ok(Some(self.nested(compiler, Self::parse_quote)?))
         }
-    
-        //if it is a vidz element, parse it using the sound element, and return back 
-        else if self.is_vidz_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_video(compiler);
-            return;
+
+        // If it is a showcase tag, parse it as a figure
+        else if self.is_figure_element(&compiler.current_tok, &compiler.lexer) {
+            Ok(Some(self.nested(compiler, Self::parse_figure)?))
         }
 
-         //if it is a newline element, parse it using the newline element, and return back 
-        else if self.is_newline_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_newline(compiler);
-            return;
+        // If it is a boxz tag, parse it as a container
+        else if self.is_container_element(&compiler.current_tok, &compiler.lexer) {
+            Ok(Some(self.nested(compiler, Self::parse_container)?))
         }
 
-        //return an error if #gimmeh is found and no bold, italics, soundz, vidz, or newline is found
+        // Report an error if #maek is found and there is neither paragraf, list, heading, grid, saidz, showcase nor boxz
         else {
-            eprintln!(
-                "Syntax error at line {}: Expected 'bold', 'italics', 'soundz', 'vidz' or 'newline', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&["paragraf", "list"], HEADING_ELEMENTS, &["grid", "saidz", "showcase", "boxz"]].concat(), &compiler.current_tok))
         }
     }
+    // If the next token found is #gimmeh,
+    else if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+
+        //get the next token to determine which tag it its
+        compiler.current_tok = compiler.next_token();
+
+        // parse the element with the parser of its tag, an error lists every element the body accepts after #gimmeh
+        Ok(Some(self.nested(compiler, |parser, compiler| parser.parse_gimmeh_element(compiler, Self::body_gimmeh_elements))?))
+    }
 
     //parse variable usage part if it is found
     else if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
-        self.parse_variable_use(compiler);
+        Ok(Some(self.parse_variable_use(compiler)?))
     }
 
     //parse a comment if a comment is found
     else if self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
-        self.parse_comment(compiler);
+        Ok(Some(self.parse_comment(compiler)?))
+
+    }
 
+    //report an error for a tag that cannot start a body element
+    else if is_tag(&compiler.current_tok) {
+        Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#maek", "#gimmeh", "#lemme", "#obtw", "#i", "#kthxbye"], &compiler.current_tok))
     }
 
     //if token does not match anything, is not empty, and is not a tag,it must be an acceptable text token, parse it as a text
     else if !compiler.current_tok.is_empty() {
-        self.parse_text(compiler);
+        self.parse_text(compiler)
+    }
+
+    else {
+        Ok(None)
     }
 }
 
 // parse the paragraf method and contents inside paragraf
-  fn parse_paragraph(&mut self, compiler: &mut LolcodeCompiler) {
-  
-    // Already consumed #MAEK, current_tok is PARAGRAF
+  fn parse_paragraph(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
-    //push the variable scope in scope stack on entering a new paragraf tag 
-    compiler.push_scope();
+    // Already consumed #MAEK, current_tok is PARAGRAF
 
     // Verify we're on PARAGRAF, else report an error to paragraf
     if !self.is_paragraph_element(&compiler.current_tok, &compiler.lexer) {
-        eprintln!(
-            "Syntax error at line {}: Expected 'paragraf', found '{}'.",
-            self.current_line, compiler.current_tok
-        );
-        std::process::exit(1);
+        return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["paragraf"], &compiler.current_tok));
     }
-    
+
+    //push the variable scope in scope stack on entering a new paragraf tag
+    compiler.push_scope();
+
+    // Remember the line of the paragraf element for source comments
+    let line = compiler.token_line();
+
     // Consume PARAGRAF and move to the paragraph content
     compiler.current_tok = compiler.next_token();
-  
+
 
     // Parse paragraph contents till the #oic end tag is found
+    // A paragraf without contents (#maek paragraf #oic) is valid and is emitted as an empty <p></p>, --pedantic reports it
+    let mut children = Vec::new();
 
     while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
-        // Report an error if tokens found are empty
+        // Report an unterminated paragraf if the input ends before #oic
         if compiler.current_tok.is_empty() {
-            eprintln!(
-                "Syntax error at line {}: Unexpected end of input in paragraph.",
-                self.current_line
-            );
-            std::process::exit(1);
+            return Err(CompileError::Unterminated { line, block: "paragraf".to_string() });
         }
 
         //parse the variable definition there is one found subsequently as defined in BNF
         if self.is_variable_start(&compiler.current_tok, &compiler.lexer) {
-            self.parse_variable_define(compiler);
+            self.parse_variable_define(compiler).map_err(|error| unterminated(error, "paragraf", line))?;
             // parse_variable_define already advances token, continue loop
         }
         else {
 
             // Parse the content and advance
-            self.parse_inner_paragraph(compiler);
+            children.extend(self.parse_inner_paragraph(compiler).map_err(|error| unterminated(error, "paragraf", line))?);
         }
     }
 
     // Consume #OIC else report an error if it is not found
     if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
-        eprintln!(
-            "Syntax error at line {}: Expected '#oic', found '{}'.",
-            self.current_line, compiler.current_tok
-        );
-        std::process::exit(1);
+        return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#oic"], &compiler.current_tok));
     }
 
     //get the next token from the compiler
     compiler.current_tok = compiler.next_token();
-    
+
     //Remove the scope from the scope stack after going out of paragraf tag
     compiler.pop_scope();
+
+    Ok(HtmlNode::Paragraph { line, children })
 }
 
 // parse inner_paragraf and its contents which include inner_text
-   fn parse_inner_paragraph(&mut self, compiler: &mut LolcodeCompiler) {
-  
-    
-    // Parse one element of paragraph content 
-    self.parse_inner_text(compiler);
-    
-    // Advance to next token, till the end
-    if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+   fn parse_inner_paragraph(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError> {
+
+
+    // Parse one element of paragraph content
+    let node = self.parse_inner_text(compiler)?;
+
+    // Elements consume their own tokens, advance past a token that is not paragraph content, till the end
+    if node.is_none() && !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
         compiler.current_tok = compiler.next_token();
     }
+
+    Ok(node)
 }
 
 //Parse the list found, if any, inside the paragraf
-    fn parse_list(&mut self, compiler: &mut LolcodeCompiler) {
-        
-        //Expect #maek, if not found -> report an error
-        if !self.is_make_start(&compiler.current_tok, &compiler.lexer)
-        {
-              eprintln!(
-                "Syntax error at line {}: Expected '#maek', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
-        }
+    fn parse_list(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
-        //get the next token from the user
-        compiler.current_tok = compiler.next_token();
+        // Already consumed #MAEK, current_tok is LIST
 
-        // if list element not found, report an error 
+        // if list element not found, report an error
          if !self.is_list_element(&compiler.current_tok, &compiler.lexer)
         {
-              eprintln!(
-                "Syntax error at line {}: Expected 'list', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+              return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["list"], &compiler.current_tok));
         }
 
+        // Remember the line of the list element for source comments
+        let line = compiler.token_line();
+
         // get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-        //parse the list items inside the list
-        self.parse_list_items(compiler);
+        //parse the list items inside the list, a list without items (#maek list #oic) is valid and is emitted as an empty <ul></ul>
+        let children = self.parse_list_items(compiler).map_err(|error| unterminated(error, "list", line))?;
 
-        // Expect #OIC at the end of list, else report an error
+        // Expect #OIC at the end of list, else report an error, an unterminated list if the input ended
+        if compiler.current_tok.is_empty() {
+            return Err(CompileError::Unterminated { line, block: "list".to_string() });
+        }
         if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
-            if compiler.current_tok.is_empty() {
-                eprintln!(
-                    "Syntax error at line {}: Expected 'oic', found '{}'.",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
-            }
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#oic"], &compiler.current_tok));
         }
 
         // Consume #OIC, get the next token from the compiler
         compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::List { line, children })
     }
 
     //function to parse list items
-    fn parse_list_items(&mut self, compiler: &mut LolcodeCompiler)
-    {
-        // if compiler token is non-empty
-        if !compiler.current_tok.is_empty()
-        {
-            // parse a single list item
-            self.parse_item(compiler);
-
-            //recursive call to parse_list_items if no token found, return control back to calling function
-            self.parse_list_items(compiler);
+    fn parse_list_items(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError> {
+        let mut items = Vec::new();
 
+        // while compiler token starts another list item, or a comment between the items, return control back to calling function after the last one
+        while self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
+            || self.is_comment_start(&compiler.current_tok, &compiler.lexer)
+        {
+            // parse a single list item or comment
+            if self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
+                items.push(self.parse_comment(compiler)?);
+            } else {
+                items.push(self.parse_item(compiler)?);
+            }
         }
+
+        Ok(items)
     }
 
-    //function to parse inner text which include - variable usage, bold, italicz, newline, soundz, vidz, list and text
-    fn parse_inner_text(&mut self, compiler: &mut LolcodeCompiler) {
+    //function to parse inner text which include - variable usage, comments, bold, italicz, newline, soundz, vidz, list and text
+    fn parse_inner_text(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError> {
 
     // If variable usage is found, parse it accordinglya and get the next token
     if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
-        self.parse_variable_use(compiler);
+        Ok(Some(self.parse_variable_use(compiler)?))
+    }
+
+    //A comment inside the paragraf is kept in place, it is emitted on its own line
+    else if self.is_comment_start(&compiler.current_tok, &compiler.lexer) {
+        Ok(Some(self.parse_comment(compiler)?))
     }
 
     //if #gimmeh is found, check to see if it is bold, italicz, newline, sounds, vidz
@@ -990,89 +2385,64 @@ impl SyntaxAnalyzer for LolcodeSyntaxAnalyzer {
 
         //get the next token from gimmeh to determine what it is
         compiler.current_tok = compiler.next_token();
-        
-        //If it is bold, call the bold function, #gimmeh and bold already comsumed
-        if self.is_bold_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_bold(compiler);
-        } 
-
-        //If it is italicz, call the italics function, #gimmeh and italics already comsumed
-        else if self.is_italics_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_italics(compiler);
-        }
-
-        //If it is newline, call the newline function, #gimmeh and newline already comsumed
-        else if self.is_newline_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_newline(compiler);
-        }
-
-        //If it is soundz, call the soundz function, #gimmeh and soundz already comsumed
-        else if self.is_soundz_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_audio(compiler);
-        } 
-
-        //If it is vidz, call the vidz function, #gimmeh and vidz already comsumed
-        else if self.is_vidz_element(&compiler.current_tok, &compiler.lexer) {
-            self.parse_video(compiler);
-        }
 
-        //report an error if anything else is found after #gimmeh except the above tags
-        else {
-            eprintln!(
-                "Syntax error at line {}: Expected 'bold', 'italics', 'newline', 'soundz', or 'vidz', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
-        }
+        //parse the element with the parser of its tag, #gimmeh already consumed, an error lists every element accepted here
+        Ok(Some(self.nested(compiler, |parser, compiler| parser.parse_gimmeh_element(compiler, Self::text_gimmeh_elements))?))
     }
 
     //if #maek tag is found, it will be a list
     else if self.is_make_start(&compiler.current_tok, &compiler.lexer) {
-        
+
         //get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-        //parse the list appropriately 
-        self.parse_list(compiler); 
+        //parse the list appropriately
+        Ok(Some(self.nested(compiler, Self::parse_list)?))
     }
 
     //If the token is non-empty and is not a tag (does not start with "#"), consume it as a text element
-    else if !compiler.current_tok.starts_with("#") {
-        self.parse_text(compiler);
+    else if !is_tag(&compiler.current_tok) {
+        self.parse_text(compiler)
+    }
+
+    else {
+        Ok(None)
     }
 }
 
 // parse the acceptable tokens in the language except tags with #, and some keywords
     //report an error if acceptable tokens are not found
-    fn parse_text(&mut self, compiler: &mut LolcodeCompiler) {
-        while !compiler.current_tok.starts_with("#") && !self.is_mkay_end(&compiler.current_tok, &compiler.lexer)
-        {  
+    fn parse_text(&mut self, compiler: &mut LolcodeCompiler) -> Result<Option<HtmlNode>, CompileError> {
+        let mut words: Vec<String> = Vec::new();
+
+        while !is_tag(&compiler.current_tok) && !self.is_mkay_end(&compiler.current_tok, &compiler.lexer)
+        {
             if compiler.current_tok.is_empty() {
-                eprintln!(
-                    "Syntax error at line {}: Unexpected end of input in bold.",
-                    self.current_line
-                );
-                std::process::exit(1);
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text", "#mkay"], ""));
             }
 
+            //collect the text token, an escaped ## is written as a literal #
+            words.push(unescape_text(&compiler.current_tok).to_string());
+
             //get the next token from the compiler
             compiler.current_tok = compiler.next_token();
         }
 
-        return; 
+        //return the text tokens as a single text node separated by spaces, none if no text was found
+        if words.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(HtmlNode::Text(words.join(" "))))
+        }
     }
 
     //function to parse list items inside a list, will contain a #gimmeh item variable definition text followed by mkay
-    fn parse_item(&mut self, compiler: &mut LolcodeCompiler) {
+    fn parse_item(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
         // consume #gimmeh, if not found report an error
           if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                    "Syntax error at line {}: expected #gimmeh found {}",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
         }
 
         //get the next token from the compiler
@@ -1081,1445 +2451,4481 @@ impl SyntaxAnalyzer for LolcodeSyntaxAnalyzer {
         //consume item, if not found report an error
   if !self.is_item_element(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                    "Syntax error at line {}: expected #gimmeh found {}",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["item"], &compiler.current_tok));
         }
 
         //get the next token from the user
         compiler.current_tok = compiler.next_token();
 
         //function to parse the inner list
-        self.parse_inner_list(compiler); 
-        
+        let children = self.parse_inner_list(compiler)?;
+
 
         //consume mkay, if not found report an error
         if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                    "Syntax error at line {}: expected #mkay found {}",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
         }
 
-    }
-
-
-    //functino to parse an inner list, contains bold, italicz, variable usage and text
-    fn parse_inner_list(&mut self, compiler: &mut LolcodeCompiler)
-    {
-        // If the compiler token is not empty
-        if !compiler.current_tok.is_empty()
-        {
-            //if the token given is #gimmeh, look whether it is bold or italics
-            if self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
-            {
-                //get the next token to see if it bold or italics
-                compiler.current_tok = compiler.next_token();
-
-                // if it is bold, parse the bold element appropriately, #gimmeh and bold already consumed
-                if self.is_bold_element(&compiler.current_tok, &compiler.lexer)
-                {
-                    self.parse_bold(compiler);
-                } 
-                
-                // if it is italicz, parse the italicz element appropriately, #gimmeh and italicz already consumed
-                else if self.is_italics_element(&compiler.current_tok, &compiler.lexer)
-                {
-                    self.parse_italics(compiler);
-                }
-            }
-
-            // if there is text, parse the text element accordingly
-            self.parse_text(compiler);
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
-            //if there is variable usage defined, parse it appropriately
-            self.parse_variable_use(compiler);
-        }
+        Ok(HtmlNode::Item(children))
     }
 
 
-    // parse the audio element, consists of #gimmeh, audio, link address and mkay tags
-    fn parse_audio(&mut self, compiler: &mut LolcodeCompiler) {
-        
-        // Expect #gimmeh - if not found report an error
-        if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer)
-        {
-            eprintln!(
-                "Syntax error at line {}: Expected '#gimmeh', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
-        }
+    //functino to parse an inner list, contains text, variable usage and inline elements in any order till the #mkay of the item
+    fn parse_inner_list(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError> {
+        self.parse_inline_contents(compiler)
+    }
 
-        // get the next token from the compiler
-        compiler.current_tok = compiler.next_token(); 
 
+    // parse the audio element, consists of #gimmeh soundz, one or more link addresses and mkay tags, #gimmeh consumed from parent functions
+    fn parse_audio(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
         // expect soundz element - if not found report an error
         if !self.is_soundz_element(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                "Syntax error at line {}: Expected 'soundz', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["soundz"], &compiler.current_tok));
         }
 
         // get the next token from the compiler
-        compiler.current_tok = compiler.next_token(); 
+        compiler.current_tok = compiler.next_token();
 
-        // Expect address
-        if !self.is_address(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected address for audio, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
-        }
+        // Expect at least one address, the following addresses are fallback sources
+        let mut addresses = Vec::new();
+        loop {
+            if !self.is_address(&compiler.current_tok, &compiler.lexer) {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["address"], &compiler.current_tok));
+            }
 
-        // get the next token from the user
-        compiler.current_tok = compiler.next_token();
+            // Reject addresses that are not web or relative addresses, or whose scheme is not in the allowed list
+            let address = compiler.current_tok.clone();
+            compiler.check_media_address(&address, compiler.token_line());
+            compiler.check_url_scheme(&address, compiler.token_line());
+            addresses.push(address);
+
+            // get the next token from the user
+            compiler.current_tok = compiler.next_token();
+
+            // Stop at #MKAY or at a token that is not another address
+            if self.is_mkay_end(&compiler.current_tok, &compiler.lexer)
+                || is_tag(&compiler.current_tok)
+                || compiler.current_tok.is_empty()
+            {
+                break;
+            }
+        }
 
         // Expect #MKAY, if not found report an error
         if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#mkay' after audio address, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
         }
 
         //get the next token from the user
         compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Audio(addresses))
     }
 
     // parse the vidz element, consists of #gimmeh vidz URL address and mkay at the end
-    fn parse_video(&mut self, compiler: &mut LolcodeCompiler) {
+    fn parse_video(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
         // expect vidz, if not found report an error
         if !self.is_vidz_element(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                "Syntax error at line {}: Expected 'vidz', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["vidz"], &compiler.current_tok));
         }
 
         // get the next token from the compiler
-        compiler.current_tok = compiler.next_token(); 
+        compiler.current_tok = compiler.next_token();
 
         // Expect address, report an error if not found
         if !self.is_address(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected address for audio, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["address"], &compiler.current_tok));
         }
 
+        // Reject addresses that are not web or relative addresses, or whose scheme is not in the allowed list
+        let address = compiler.current_tok.clone();
+        compiler.check_media_address(&address, compiler.token_line());
+        compiler.check_url_scheme(&address, compiler.token_line());
+
         // get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
         // Expect #MKAY, if not found report an error
         if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#mkay' after audio address, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
         }
 
         //get the next token from the user
         compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Video(address))
     }
 
     //parse a newline tag,has a form #gimmeh newline,  #gimmeh consumed already from parent functions
-    fn parse_newline(&mut self, compiler: &mut LolcodeCompiler) {
-      
+    fn parse_newline(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
         //Expect #gimmeh, if not found report an error
         if !self.is_newline_element(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                "Syntax error at line {}: Expected 'newline', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["newline"], &compiler.current_tok));
         }
-    }
 
-    //parse a bold function, has a form #gimmeh bold text variable_def #mkay, #gimmeh consumed from parent functions
-    fn parse_bold(&mut self, compiler: &mut LolcodeCompiler) {
-        // Already consumed #GIMMEH from previous functions
+        //Consume newline, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
+        Ok(HtmlNode::Newline)
+    }
 
-        //Expect bold, if not found report an error
-        if !self.is_bold_element(&compiler.current_tok, &compiler.lexer)
+    //parse a divider tag, has a form #gimmeh divider, #gimmeh consumed already from parent functions
+    fn parse_hrule(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        //Expect divider, if not found report an error
+        if !self.is_hrule_element(&compiler.current_tok, &compiler.lexer)
         {
-            eprintln!(
-                    "Syntax error at line {}: expected bold found {}",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["divider"], &compiler.current_tok));
         }
 
-        //get the next token from the compiler
+        //Consume divider, get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-       
-        //if variable usage is found, parse it accordingly
-            if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
-                self.parse_variable_use(compiler);
-            } else {
-
-                //If non-tag text is found, parse it as text
-                self.parse_text(compiler);
-            }
-        
-
-        // Consume #MKAY to signal end of bold element
-        compiler.current_tok = compiler.next_token();
+        Ok(HtmlNode::HRule)
     }
 
-    //parse a italicz function, has a form #gimmeh italicz text variable_def #mkay, #gimmeh consumed from parent functions
-    fn parse_italics(&mut self, compiler: &mut LolcodeCompiler) {
-        //Already consumed #GIMMEH
+    //Parse a quote, has a form #maek saidz followed by the same contents as a paragraf and #oic, #maek consumed from parent functions
+    fn parse_quote(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
-        //expect #italicz, if not found report an error
-        if !self.is_italics_element(&compiler.current_tok, &compiler.lexer)
-        {
-            eprintln!(
-                    "Syntax error at line {}: expected italics found {}",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+        // Already consumed #MAEK, current_tok is SAIDZ
+        if !self.is_quote_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["saidz"], &compiler.current_tok));
         }
 
-        //get the next token from the compiler
+        //push the variable scope in scope stack on entering a new saidz tag
+        compiler.push_scope();
+
+        // Remember the line of the saidz element for source comments
+        let line = compiler.token_line();
+
+        // Consume SAIDZ and move to the quote content
         compiler.current_tok = compiler.next_token();
 
-        // Parse variable definition if found one
-            if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
-                self.parse_variable_use(compiler);
-            } 
-            //Parse text if no tags are found
-            else {
-                self.parse_text(compiler);
+        // Parse quote contents till the #oic end tag is found, the same way as paragraph contents
+        let mut children = Vec::new();
+        while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            // Report an unterminated saidz if the input ends before #oic
+            if compiler.current_tok.is_empty() {
+                return Err(CompileError::Unterminated { line, block: "saidz".to_string() });
             }
-        
 
-        // Consume #MKAY to signal end of italicz element
+            //parse the variable definition if there is one, else the content of the quote
+            if self.is_variable_start(&compiler.current_tok, &compiler.lexer) {
+                self.parse_variable_define(compiler).map_err(|error| unterminated(error, "saidz", line))?;
+            } else {
+                children.extend(self.parse_inner_paragraph(compiler).map_err(|error| unterminated(error, "saidz", line))?);
+            }
+        }
+
+        // Consume #OIC, get the next token from the compiler
         compiler.current_tok = compiler.next_token();
+
+        //Remove the scope from the scope stack after going out of saidz tag
+        compiler.pop_scope();
+
+        Ok(HtmlNode::Quote { line, children })
     }
 
-    //Function to parse variable definition, has a form #i haz variable_name #it iz variable_definition
-    fn parse_variable_define(&mut self, compiler: &mut LolcodeCompiler) {
-        
-        //get next token from the compiler and convert it to lowercase
-        let var_keyword = compiler.current_tok.to_lowercase();
-      
+    //Parse a maths element, has a form #gimmeh maths variable_name variable_name #mkay, #gimmeh consumed from parent functions
+    //Both variables must be numbers, the element is emitted as their sum
+    fn parse_maths(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        //expect maths, if not found report an error
+        if !self.is_maths_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["maths"], &compiler.current_tok));
+        }
 
-        // If we saw #I, expect HAZ
-        if var_keyword == "#i" {
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
-            //If there is not haz, report a syntax error
-            if compiler.current_tok.to_lowercase() != "haz" {
-                eprintln!(
-                    "Syntax error at line {}: Expected 'haz' after '#i', found '{}'.",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
+        // Look up both variables, record an error if one is not defined or is not a number, the rest of the element is still parsed
+        let mut sum: Option<u64> = Some(0);
+        for _operand in 0..2 {
+            if !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["variable identifier"], &compiler.current_tok));
             }
 
+            let var_name = compiler.current_tok.clone();
+            let line = compiler.token_line();
+            let number = match compiler.lookup_variable(&var_name) {
+                Some(variable) => {
+                    variable.used = true;
+                    match variable.kind {
+                        VarKind::Number(number) => Some(number),
+                        VarKind::Text => {
+                            let message = format!("Variable '{}' is not a number, only number variables can be added with maths.", var_name);
+                            compiler.errors.push(CompileError::Semantic { line, message });
+                            None
+                        }
+                    }
+                }
+                None => {
+                    compiler.errors.push(CompileError::UnresolvedVariable { line, name: var_name.clone() });
+                    None
+                }
+            };
+            sum = sum.zip(number).and_then(|(sum, number)| sum.checked_add(number));
+
             //get the next token from the compiler
             compiler.current_tok = compiler.next_token();
+        }
 
+        // Expect #MKAY at the end
+        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
         }
+        compiler.current_tok = compiler.next_token();
 
-        // Expect variable identifier to validate variable_name follows naming conventions, if it is empty or does not follow naming rules, report a syntax error
-        if !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected variable identifier, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+        Ok(HtmlNode::Text(sum.map(|sum| sum.to_string()).unwrap_or_default()))
+    }
+
+    //Parse a code element, has a form #gimmeh codez text #mkay, #gimmeh consumed from parent functions
+    //Every token till #mkay is code, the spacing between the tokens is rebuilt from their lines and columns
+    fn parse_code(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        //expect codez, if not found report an error
+        if !self.is_code_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["codez"], &compiler.current_tok));
         }
 
-        //Consume the variable name for storing it in scope stack
-        let var_name = compiler.current_tok.clone();
+        // Remember the line of the codez element, reported if the input ends inside it
+        let line = compiler.token_line();
 
         //get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
+        // Line and column right after the previous token of the code
+        let mut code = String::new();
+        let mut end: Option<(usize, usize)> = None;
+        while !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            if compiler.current_tok.is_empty() {
+                return Err(CompileError::Unterminated { line, block: "codez".to_string() });
+            }
 
+            // Keep the line breaks and the spaces between this token and the previous one
+            let (token_line, token_column) = (compiler.token_line(), compiler.token_column());
+            if let Some((end_line, end_column)) = end {
+                if token_line > end_line {
+                    code.push_str(&"\n".repeat(token_line - end_line));
+                    code.push_str(&" ".repeat(token_column.saturating_sub(1)));
+                } else {
+                    code.push_str(&" ".repeat(token_column.saturating_sub(end_column).max(1)));
+                }
+            }
+            code.push_str(unescape_text(&compiler.current_tok));
+            end = Some((token_line, token_column + compiler.current_tok.chars().count()));
 
-        // Check for #IT IZ (value assignment)
-        let var_value = if self.is_variable_mid(&compiler.current_tok, &compiler.lexer) {
-
-            // convert the keyword into lowercase
-            let mid_keyword = compiler.current_tok.to_lowercase();
-
-            //get the next token from user
+            //get the next token from the compiler
             compiler.current_tok = compiler.next_token();
+        }
 
-            // If we saw #IT, expect IZ
-            if mid_keyword == "#it" {
+        // Consume #MKAY to signal end of codez element
+        compiler.current_tok = compiler.next_token();
 
-                //if iz is not found, report an error
-                if compiler.current_tok.to_lowercase() != "iz" {
-                    eprintln!(
-                        "Syntax error at line {}: Expected 'iz' after '#it', found '{}'.",
-                        self.current_line, compiler.current_tok
-                    );
-                    std::process::exit(1);
-                }
+        Ok(HtmlNode::Code(code))
+    }
 
-                //get next token from the user
-                compiler.current_tok = compiler.next_token();
-            }
+    //parse a bold function, has a form #gimmeh bold text variable_def #mkay, #gimmeh consumed from parent functions
+    fn parse_bold(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+        // Already consumed #GIMMEH from previous functions
 
-            // Expect value in the form of text or acceptable text items without spaces, report an error if no such value is found
-            if !self.is_text(&compiler.current_tok, &compiler.lexer)
-                && !self.is_address(&compiler.current_tok, &compiler.lexer)
-            {
-                eprintln!(
-                    "Syntax error at line {}: Expected value after 'iz', found '{}'.",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
-            }
 
-            //Consume the value of the variable
-            let value = compiler.current_tok.clone();
+        //Expect bold, if not found report an error
+        if !self.is_bold_element(&compiler.current_tok, &compiler.lexer)
+        {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["bold"], &compiler.current_tok));
+        }
 
-            //Get the next token from the compiler
-            compiler.current_tok = compiler.next_token();
+        // Remember the line of the bold element, reported if the input ends inside it
+        let line = compiler.token_line();
 
-            //get the #mkay token, if not found, report an error
-            if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
-                eprintln!(
-                    "Syntax error at line {}: Expected '#mkay' after variable value, found '{}'.",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
-            }
-            //Add statement for mkay
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
-            compiler.current_tok = compiler.next_token();
+        //parse text, variable usage and nested inline elements till #mkay
+        let children = self.parse_inline_contents(compiler).map_err(|error| unterminated(error, "bold", line))?;
 
-            //Include an option to store value of variable
-            Some(value)
-        } else {
-            //If no value found, assign none
-            None
-        };
+        // Consume #MKAY to signal end of bold element
+        compiler.current_tok = compiler.next_token();
 
-        //function to handle semantic analysis - described later in the code
-        compiler.declare_variable(var_name, var_value, self.current_line);
-       
+        Ok(HtmlNode::Bold(children))
     }
 
-        //Function to parse variable usage, has a form #lemme see variable_name mkay
-    fn parse_variable_use(&mut self, compiler: &mut LolcodeCompiler) {
+    //parse a italicz function, has a form #gimmeh italicz text variable_def #mkay, #gimmeh consumed from parent functions
+    fn parse_italics(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+        //Already consumed #GIMMEH
 
-        // Expect #LEMME , if not found report a syntax error #lemme not found
-        if !self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#lemme' or 'see', found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+        //expect #italicz, if not found report an error
+        if !self.is_italics_element(&compiler.current_tok, &compiler.lexer)
+        {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["italics"], &compiler.current_tok));
         }
 
-        // Get the variable name after #lemme
-        let var_keyword = compiler.current_tok.to_lowercase();
+        // Remember the line of the italics element, reported if the input ends inside it
+        let line = compiler.token_line();
+
+        //get the next token from the compiler
         compiler.current_tok = compiler.next_token();
 
-        // If we saw #LEMME, expect SEE
-        if var_keyword == "#lemme" {
+        //parse text, variable usage and nested inline elements till #mkay
+        let children = self.parse_inline_contents(compiler).map_err(|error| unterminated(error, "italics", line))?;
 
-            //If see not found, report an error
-            if compiler.current_tok.to_lowercase() != "see" {
-                eprintln!(
-                    "Syntax error at line {}: Expected 'see' after '#lemme', found '{}'.",
-                    self.current_line, compiler.current_tok
-                );
-                std::process::exit(1);
-            }
+        // Consume #MKAY to signal end of italicz element
+        compiler.current_tok = compiler.next_token();
 
-            //get the next token from the compiler
-            compiler.current_tok = compiler.next_token();
-        }
+        Ok(HtmlNode::Italics(children))
+    }
 
-        // Expect variable identifier, if missing report an error
-        if !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected variable identifier, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+    //parse a shout function, has a form #gimmeh shout text variable_def #mkay, #gimmeh consumed from parent functions
+    fn parse_shout(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+        //Already consumed #GIMMEH
+
+        //expect shout, if not found report an error
+        if !self.is_shout_element(&compiler.current_tok, &compiler.lexer)
+        {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["shout"], &compiler.current_tok));
         }
 
-        //Get the variable name as the next token
-        let var_name = compiler.current_tok.clone();
+        // Remember the line of the shout element, reported if the input ends inside it
+        let line = compiler.token_line();
 
-        // Check if variable is defined using lookup_variable, if already defined report an error, or if not defined report an error
-        if compiler.lookup_variable(&var_name).is_none() {
-            eprintln!(
-                "Semantic error at line {}: Variable '{}' is used before being defined.",
-                self.current_line, var_name
-            );
-            eprintln!(
-                "  --> Variable '{}' has not been declared in the current scope.",
-                var_name
-            );
-            eprintln!(
-                "  --> Use '#I HAZ {}' or 'HAZ {}' to declare the variable before using it.",
-                var_name, var_name
-            );
-            std::process::exit(1);
-        }
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
-        //Variable defined successfully, get the next token
+        //parse text, variable usage and nested inline elements till #mkay
+        let children = self.parse_inline_contents(compiler).map_err(|error| unterminated(error, "shout", line))?;
+
+        // Consume #MKAY to signal end of shout element
         compiler.current_tok = compiler.next_token();
 
-        //If next token not mkay, report an error 
-        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
-            eprintln!(
-                "Syntax error at line {}: Expected '#mkay' after variable usage, found '{}'.",
-                self.current_line, compiler.current_tok
-            );
-            std::process::exit(1);
+        Ok(HtmlNode::Shout(children))
+    }
+
+    //parse an image, has a form #gimmeh piccy address alt_text #mkay, #gimmeh consumed from parent functions, the alt text can be left out
+    fn parse_image(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // expect piccy, if not found report an error
+        if !self.is_image_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["piccy"], &compiler.current_tok));
         }
 
         // get the next token from the compiler
         compiler.current_tok = compiler.next_token();
-    }
-}
 
-//Implementation for lolcode compiler
-impl LolcodeCompiler {
-    pub fn new() -> Self {
-        Self {
-            lexer: LolcodeLexicalAnalyzer::new(""),
-            parser: LolcodeSyntaxAnalyzer::new(),
-            current_tok: String::new(),
-            scope_stack: vec![HashMap::new()],
-            language_tokens: vec![],
+        // Expect address, report an error if not found
+        if !self.is_address(&compiler.current_tok, &compiler.lexer) || is_tag(&compiler.current_tok) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["address"], &compiler.current_tok));
         }
-    }
 
-    // Get the first token and validate if it is empty
-    fn start(&mut self) {
-        // Get the first token
-        self.current_tok = self.next_token();
+        // Reject addresses that are not web or relative addresses, or whose scheme is not in the allowed list
+        let address = compiler.current_tok.clone();
+        compiler.check_media_address(&address, compiler.token_line());
+        compiler.check_url_scheme(&address, compiler.token_line());
 
-        // Report an error if it is empty
-        if self.current_tok.is_empty() {
-            eprintln!("User error: The provided sentence is empty.");
-            std::process::exit(1);
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // The text till #mkay is the alt text of the image
+        let alt = match self.parse_text(compiler)? {
+            Some(HtmlNode::Text(text)) => text,
+            _ => String::new(),
+        };
+
+        // Expect #MKAY, if not found report an error
+        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
         }
+
+        //get the next token from the user
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Image { address, alt })
     }
 
-    // Parse the lolcode document
-    fn lolcode(&mut self) {
-        // Document should start with #HAI, if not report an error
-        if !self
-            .lexer
-            .head_start
-            .iter()
-            .any(|h| h == &self.current_tok.to_lowercase())
-        {
-            eprintln!(
-                "Syntax error at line {}: Expected document start '#hai', found '{}'.",
-                self.parser.current_line, self.current_tok
-            );
-            std::process::exit(1);
+    //parse a figure, has a form #maek showcase #gimmeh piccy address alt_text #mkay caption #oic, #maek consumed from parent functions
+    fn parse_figure(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // expect showcase, if not found report an error
+        if !self.is_figure_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["showcase"], &compiler.current_tok));
         }
 
+        // Remember the line of the showcase element for source comments
+        let line = compiler.token_line();
+
         // get the next token from the compiler
-        self.current_tok = self.next_token();
+        compiler.current_tok = compiler.next_token();
 
-        // Parse the document structure
-        //Initialize the parser
-        let mut parser = std::mem::replace(&mut self.parser, LolcodeSyntaxAnalyzer::new());
+        // The figure starts with its image, expect #gimmeh piccy
+        if compiler.current_tok.is_empty() {
+            return Err(CompileError::Unterminated { line, block: "showcase".to_string() });
+        }
+        if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
+        }
 
-        // Parse the lolcode document with parser
-        parser.parse_lolcode(self);
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
 
-        //Assign the parser to the object
-        self.parser = parser;
+        let image = self.parse_image(compiler).map_err(|error| unterminated(error, "showcase", line))?;
 
-        // Document should end with #KTHXBYE, report an error if #kthxbye not found at the end
-        if !self
-            .lexer
-            .head_end
-            .iter()
-            .any(|h| h == &self.current_tok.to_lowercase())
-        {
-            eprintln!(
-                "Syntax error at line {}: Expected document end '#kthxbye', found '{}'.",
-                self.parser.current_line, self.current_tok
-            );
-            std::process::exit(1);
+        // The content after the image till #oic is the caption, a figure without a caption has no figcaption
+        let mut caption = Vec::new();
+        while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            // Report an unterminated showcase if the input ends before #oic
+            if compiler.current_tok.is_empty() {
+                return Err(CompileError::Unterminated { line, block: "showcase".to_string() });
+            }
+
+            caption.extend(self.parse_inner_paragraph(compiler).map_err(|error| unterminated(error, "showcase", line))?);
         }
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Figure { line, image: Box::new(image), caption })
     }
 
-    /*****
-     * Task 3 - Semantic Analyzer part - Static variable scope resolution methods
-     * 1. Push scope - Push the new variable scope into the stack
-     * 2. Pop scope - Remove the new variable scope from the stack
-     * 3. Declare variable - semantic analyzer validating variable re-declaration and assignment to scope
-     * 4. lookup - function supporting retrieval of the values of variable defined in the scopre
-     */
+    //parse a container, has a form #maek boxz .class body_content #oic, #maek consumed from parent functions
+    //The class is optional, it is written after a dot like a CSS selector so the first word of the content is never taken for it, the content is anything the body accepts
+    fn parse_container(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
 
-    // Push the new scope to the stack
-    fn push_scope(&mut self) {
-        self.scope_stack.push(HashMap::new());
+        // expect boxz, if not found report an error
+        if !self.is_container_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["boxz"], &compiler.current_tok));
+        }
+
+        //push the variable scope in scope stack on entering a new boxz tag
+        compiler.push_scope();
+
+        // Remember the line of the boxz element for source comments
+        let line = compiler.token_line();
 
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Consume the class if there is one, .name is the class name when name is a variable identifier, anything else is content
+        let class = match compiler.current_tok.strip_prefix('.') {
+            Some(name) if self.is_variable_identifier(name, &compiler.lexer) => {
+                let class = name.to_string();
+                compiler.current_tok = compiler.next_token();
+                Some(class)
+            }
+            _ => None,
+        };
+
+        // Parse the body content till the #oic end tag is found, an empty boxz is valid
+        let mut children = Vec::new();
+        while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            // Report an unterminated boxz if the input ends before #oic
+            if compiler.current_tok.is_empty() {
+                return Err(CompileError::Unterminated { line, block: "boxz".to_string() });
+            }
+
+            children.extend(self.parse_inner_body(compiler).map_err(|error| unterminated(error, "boxz", line))?);
+        }
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        //Remove the scope from the scope stack after going out of boxz tag
+        compiler.pop_scope();
+
+        Ok(HtmlNode::Container { line, class, children })
     }
 
-    // Pop the scope from the stack if there are more than one stack, one scope for global variables 
-    fn pop_scope(&mut self) {
-        if self.scope_stack.len() > 1 {
-            self.scope_stack.pop();
+    //parse a body block, has a form #maek body body_content #oic, the content is anything the body accepts and is returned without a node of its own
+    fn parse_body_block(&mut self, compiler: &mut LolcodeCompiler) -> Result<Vec<HtmlNode>, CompileError> {
+
+        // Expect #MAEK, if #MAEK not found report a syntax error
+        if !self.is_make_start(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#maek"], &compiler.current_tok));
+        }
+
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // expect body, if not found report an error
+        if !self.is_body_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["body"], &compiler.current_tok));
+        }
+
+        // Remember the line of the body element for the unterminated error
+        let line = compiler.token_line();
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Parse the body content till the #oic end tag is found, an empty body is valid
+        let mut children = Vec::new();
+        while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            // Report an unterminated body if the input or the document ends before #oic
+            if compiler.current_tok.is_empty() || self.is_document_end(&compiler.current_tok, &compiler.lexer) {
+                return Err(CompileError::Unterminated { line, block: "body".to_string() });
+            }
+
+            children.extend(self.parse_inner_body(compiler).map_err(|error| unterminated(error, "body", line))?);
         }
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(children)
     }
 
-    // Declare a variable in the current scope with semantic analysis to validate for re-declaration and insert it into scope stack
-    fn declare_variable(&mut self, name: String, value: Option<String>, line: usize) {
+    //Function to parse variable definition, has a form #i haz variable_name #it iz variable_definition
+    fn parse_variable_define(&mut self, compiler: &mut LolcodeCompiler) -> Result<(), CompileError> {
+        
+        //The words of the variable tags come from the lexer, a dialect can rename them
+        let [start_tag, start_word] = tag_words(&compiler.lexer.variable_start);
+        let [mid_tag, mid_word] = tag_words(&compiler.lexer.variable_mid);
+
+        //Expect #I first, a bare haz is missing its #i, report a syntax error
+        if compiler.current_tok.to_lowercase() != start_tag {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&start_tag], &compiler.current_tok));
+        }
+
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // After #I, expect HAZ, if there is not haz, report a syntax error
+        if !start_word.is_empty() {
+            if compiler.current_tok.to_lowercase() != start_word {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&start_word], &compiler.current_tok));
+            }
+
+            //get the next token from the compiler
+            compiler.current_tok = compiler.next_token();
+        }
+
+        // Expect variable identifier to validate variable_name follows naming conventions, if it is empty or does not follow naming rules, report a syntax error
+        if !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["variable identifier"], &compiler.current_tok));
+        }
+
+        //Consume the variable name for storing it in scope stack, the variable is defined at the line of its name
+        let var_name = compiler.current_tok.clone();
+        let line = compiler.token_line();
+
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+
+
+        // Check for #IT IZ (value assignment)
+        let var_value = if self.is_variable_mid(&compiler.current_tok, &compiler.lexer) {
+
+            //Expect #IT first, a bare iz is missing its #it, report a syntax error
+            if compiler.current_tok.to_lowercase() != mid_tag {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&mid_tag], &compiler.current_tok));
+            }
+
+            //get the next token from user
+            compiler.current_tok = compiler.next_token();
+
+            // After #IT, expect IZ, if iz is not found, report an error
+            if !mid_word.is_empty() {
+                if compiler.current_tok.to_lowercase() != mid_word {
+                    return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&mid_word], &compiler.current_tok));
+                }
+
+                //get next token from the user
+                compiler.current_tok = compiler.next_token();
+            }
+
+            //Consume the words of the value till #mkay, they are joined by single spaces
+            let mut words: Vec<String> = Vec::new();
+            while !is_tag(&compiler.current_tok)
+                && (self.is_text(&compiler.current_tok, &compiler.lexer)
+                    || self.is_address(&compiler.current_tok, &compiler.lexer))
+            {
+                words.push(compiler.current_tok.clone());
+
+                //Get the next token from the compiler
+                compiler.current_tok = compiler.next_token();
+            }
+
+            // Expect value in the form of text or acceptable text items, report an error if no such value is found
+            if words.is_empty() {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["value"], &compiler.current_tok));
+            }
+            let value = words.join(" ");
+
+            //get the #mkay token, if not found, report an error
+            if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
+            }
+            //Add statement for mkay
+
+            compiler.current_tok = compiler.next_token();
+
+            //Include an option to store value of variable
+            Some(value)
+        } else {
+            //If no value found, assign none, the #mkay that ends a declaration without a value is consumed if it is written
+            if self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+                compiler.current_tok = compiler.next_token();
+            }
+            None
+        };
+
+        //Values made of digits only are numbers, they can be added with #gimmeh maths
+        let kind = classify_value(var_value.as_deref());
+
+        //function to handle semantic analysis - described later in the code
+        compiler.declare_variable(var_name, var_value, kind, line);
+        Ok(())
        
-       //Check if there is any variable with the same name in the current scope, if so report an error
-        if let Some(current_scope) = self.scope_stack.last_mut() {
-            if current_scope.contains_key(&name) {
-                let existing = &current_scope[&name];
-                eprintln!(
-                    "Semantic error at line {}: Variable '{}' is already defined at line {} in the current scope.",
-                    line, name, existing.line_defined
-                );
-                std::process::exit(1);
+    }
+
+        //Function to parse variable usage, has a form #lemme see variable_name mkay
+    fn parse_variable_use(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        //The words of the variable tag come from the lexer, a dialect can rename them
+        let [end_tag, end_word] = tag_words(&compiler.lexer.variable_end);
+
+        // Expect #LEMME , if not found report a syntax error #lemme not found
+        if !self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&end_tag, &end_word], &compiler.current_tok));
+        }
+
+        // Get the variable name after #lemme
+        let var_keyword = compiler.current_tok.to_lowercase();
+        compiler.current_tok = compiler.next_token();
+
+        // If we saw #LEMME, expect SEE
+        if var_keyword == end_tag && !end_word.is_empty() {
+
+            //If see not found, report an error
+            if compiler.current_tok.to_lowercase() != end_word {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &[&end_word], &compiler.current_tok));
             }
 
-            //Validation complete, insert the variable into the current scope
-            current_scope.insert(
-                name.clone(),
-                VariableInfo {
-                    name,
-                    value,
-                    line_defined: line,
-                },
-            );
+            //get the next token from the compiler
+            compiler.current_tok = compiler.next_token();
+        }
 
+        // Expect variable identifier, if missing report an error
+        if !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["variable identifier"], &compiler.current_tok));
         }
-            
 
+        //Get the variable name as the next token
+        let var_name = compiler.current_tok.clone();
+
+        // Check if variable is defined using lookup_variable and keep the value it has in the current scope for HTML conversion
+        // record an unresolved variable error if it is not defined, the rest of the use is still parsed
+        let value = match compiler.lookup_variable(&var_name) {
+            Some(variable) => {
+                variable.used = true;
+                //A variable declared without #it iz has no value, its use emits nothing
+                Some(variable.value.clone().unwrap_or_default())
+            }
+            None => {
+                compiler.errors.push(CompileError::UnresolvedVariable { line: compiler.token_line(), name: var_name.clone() });
+                None
+            }
+        };
+        let line = compiler.token_line();
+
+        //Variable defined successfully, get the next token
+        compiler.current_tok = compiler.next_token();
+
+        //If next token not mkay, report an error 
+        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
+        }
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::VarUse { name: var_name, value, line })
     }
 
-    //Function to retrieve values of the variables, retrieves the value from the innermost scope for a variable
-    fn lookup_variable(&self, name: &str) -> Option<&VariableInfo> {
-        // Search from innermost to outermost scope, switch to outerscope if value not found in local scope
-        for scope in self.scope_stack.iter().rev() {
-            if let Some(var_info) = scope.get(name) {
-                return Some(var_info);
+    // parse a heading, #maek bighead (or medhead ... teenyhead) followed by text and variable usage till #oic
+    fn parse_heading(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Already consumed #MAEK, current_tok is the heading element, report an error if it is not one
+        let Some(level) = self.heading_level(&compiler.current_tok, &compiler.lexer) else {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), HEADING_ELEMENTS, &compiler.current_tok));
+        };
+
+        // Remember the line of the heading element for source comments
+        let line = compiler.token_line();
+
+        // Consume the heading element and move to the heading content
+        compiler.current_tok = compiler.next_token();
+
+        // Parse text and variable usage till the #oic end tag is found
+        let mut children = Vec::new();
+        while !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            // Report an error if tokens found are empty
+            if compiler.current_tok.is_empty() {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#oic"], ""));
+            }
+
+            //parse variable usage if found, else the token must be text
+            if self.is_variable_end(&compiler.current_tok, &compiler.lexer) {
+                children.push(self.parse_variable_use(compiler)?);
+            } else if !is_tag(&compiler.current_tok) {
+                children.extend(self.parse_text(compiler)?);
+            }
+
+            //report an error for any other tag inside a heading
+            else {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text", "#lemme", "#oic"], &compiler.current_tok));
             }
         }
-        //return None if value not found
-        None
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Heading { level, line, children })
+    }
+
+    // parse the linkz element, consists of #gimmeh linkz URL address, the link text and mkay at the end, #gimmeh consumed from parent functions
+    fn parse_link(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // expect linkz, if not found report an error
+        if !self.is_link_element(&compiler.current_tok, &compiler.lexer)
+        {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["linkz"], &compiler.current_tok));
+        }
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect address, report an error if not found
+        if is_tag(&compiler.current_tok) || !self.is_address(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["address"], &compiler.current_tok));
+        }
+
+        // Reject addresses whose scheme is not in the allowed list
+        let address = compiler.current_tok.clone();
+        compiler.check_url_scheme(&address, compiler.token_line());
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect at least one text token before #MKAY, report an error if anything else is found
+        let mut words: Vec<String> = Vec::new();
+        while !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            if is_tag(&compiler.current_tok) || !self.is_text(&compiler.current_tok, &compiler.lexer) {
+                return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text", "#mkay"], &compiler.current_tok));
+            }
+            words.push(compiler.current_tok.clone());
+            compiler.current_tok = compiler.next_token();
+        }
+        if words.is_empty() {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text"], &compiler.current_tok));
+        }
+
+        //get the next token from the user
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Link { address, text: words.join(" ") })
+    }
+
+    //Parse the description of the head, has a form #gimmeh describ text #mkay, only allowed inside the head
+    fn parse_description(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Expect #GIMMEH, if #gimmeh is not found - report an error
+        if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect DESCRIB, if it is not found - report an error
+        if !self.is_description_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["describ"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Consume the text of the description, a tag other than #mkay cannot be part of it
+        let text = match self.parse_text(compiler)? {
+            Some(HtmlNode::Text(text)) => text,
+            _ => return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["text"], &compiler.current_tok)),
+        };
+
+        // Expect #MKAY at the end
+        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
+        }
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Description(text))
+    }
+
+    //parse a stylez function, has a form #gimmeh stylez class_name text #mkay, #gimmeh consumed from parent functions
+    fn parse_styled(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+        //Already consumed #GIMMEH
+
+        //expect stylez, if not found report an error
+        if !self.is_styled_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["stylez"], &compiler.current_tok));
+        }
+
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        //Expect the class name, it follows the variable naming rules
+        if is_tag(&compiler.current_tok) || !self.is_variable_identifier(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["class name"], &compiler.current_tok));
+        }
+        let class = compiler.current_tok.clone();
+
+        //get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        //parse text, variable usage and nested inline elements till #mkay
+        let children = self.parse_inline_contents(compiler)?;
+
+        // Consume #MKAY to signal end of stylez element
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Styled { class, children })
+    }
+
+    //Parse a stylesheet link of the head, has a form #gimmeh dressup address #mkay
+    fn parse_stylesheet(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Expect #GIMMEH, if #gimmeh is not found - report an error
+        if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect DRESSUP, if it is not found - report an error
+        if !self.is_stylesheet_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["dressup"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect address, report an error if not found
+        if is_tag(&compiler.current_tok) || !self.is_address(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["address"], &compiler.current_tok));
+        }
+
+        // Reject addresses whose scheme is not in the allowed list
+        let address = compiler.current_tok.clone();
+        compiler.check_url_scheme(&address, compiler.token_line());
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect #MKAY at the end
+        if !self.is_mkay_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#mkay"], &compiler.current_tok));
+        }
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Stylesheet(address))
+    }
+
+    //Parse a table, has a form #maek grid followed by its rows and #oic, #maek consumed from parent functions
+    fn parse_table(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Already consumed #MAEK, current_tok is GRID
+        if !self.is_table_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["grid"], &compiler.current_tok));
+        }
+
+        // Remember the line of the grid element for source comments
+        let line = compiler.token_line();
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Every #maek inside the grid opens a row
+        let mut children = Vec::new();
+        while self.is_make_start(&compiler.current_tok, &compiler.lexer) {
+            compiler.current_tok = compiler.next_token();
+            children.push(self.parse_row(compiler)?);
+        }
+
+        // Expect #OIC at the end of the grid, else report an error
+        if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#maek", "#oic"], &compiler.current_tok));
+        }
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Table { line, children })
+    }
+
+    //Parse a table row, has a form #maek roe followed by its cells and #oic, #maek consumed from parse_table
+    fn parse_row(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Already consumed #MAEK, current_tok is ROE
+        if !self.is_row_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["roe"], &compiler.current_tok));
+        }
+
+        // get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Every #gimmeh inside the row starts a cell
+        let mut children = Vec::new();
+        while self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+            children.push(self.parse_cell(compiler)?);
+        }
+
+        // Expect #OIC at the end of the row, else report an error
+        if !self.is_oic_end(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh", "#oic"], &compiler.current_tok));
+        }
+
+        // Consume #OIC, get the next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Row(children))
+    }
+
+    //Parse a table cell, has a form #gimmeh sel text #mkay, the text can contain variable usage and inline elements
+    fn parse_cell(&mut self, compiler: &mut LolcodeCompiler) -> Result<HtmlNode, CompileError> {
+
+        // Expect #GIMMEH, if #gimmeh is not found - report an error
+        if !self.is_gimmeh_start(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["#gimmeh"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        // Expect SEL, if it is not found - report an error
+        if !self.is_cell_element(&compiler.current_tok, &compiler.lexer) {
+            return Err(syntax_error(compiler.token_line(), compiler.token_column(), &["sel"], &compiler.current_tok));
+        }
+
+        //get next token from the compiler
+        compiler.current_tok = compiler.next_token();
+
+        //parse text, variable usage and nested inline elements till #mkay
+        let children = self.parse_inline_contents(compiler)?;
+
+        // Consume #MKAY to signal end of the cell
+        compiler.current_tok = compiler.next_token();
+
+        Ok(HtmlNode::Cell(children))
+    }
+}
+
+// Number of single character insertions, deletions and substitutions that turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Check whether a token is a tag, a token starting with ## is text that starts with a literal # instead
+fn is_tag(token: &str) -> bool {
+    token.starts_with('#') && !token.starts_with("##")
+}
+
+// The text a token stands for, ## at the start of a token is written as a single #
+fn unescape_text(token: &str) -> &str {
+    if token.starts_with("##") {
+        &token[1..]
+    } else {
+        token
+    }
+}
+
+// Escape the characters that have a meaning in HTML, used for all text and variable values emitted by to_html
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Join the lines of the html into one line without their indentation, a space is kept between two lines of text
+// Every line break of the html is between tags or around a run of text, so the text of a run is left as it is
+// The line breaks and spaces inside a code element are part of the code, those lines are kept as they are
+fn minify_html(html: &str, code_tag: &str) -> String {
+    let code_start = format!("<{}>", code_tag);
+    let code_end = format!("</{}>", code_tag);
+    let mut minified = String::with_capacity(html.len());
+    let mut in_code = false;
+
+    for line in html.lines() {
+        //A line is still in code if the last code element it opens is not closed, None is before any position
+        let was_in_code = in_code;
+        in_code = match (line.rfind(&code_start), line.rfind(&code_end)) {
+            (None, None) => was_in_code,
+            (start, end) => start > end,
+        };
+
+        if was_in_code {
+            minified.push('\n');
+            minified.push_str(line);
+            continue;
+        }
+
+        //The end of a line that opens code is in the code
+        let line = if in_code { line.trim_start() } else { line.trim() };
+        if line.is_empty() {
+            continue;
+        }
+        if !minified.is_empty() && !minified.ends_with('>') && !line.starts_with('<') {
+            minified.push(' ');
+        }
+        minified.push_str(line);
+    }
+    minified
+}
+
+// Two spaces of indentation for every nesting level of the html output
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+// The child nodes of a node in document order, the image of a figure comes before its caption
+fn node_children(node: &HtmlNode) -> Vec<&HtmlNode> {
+    match node {
+        HtmlNode::Document(children)
+        | HtmlNode::Head { children, .. }
+        | HtmlNode::Title(children)
+        | HtmlNode::Paragraph { children, .. }
+        | HtmlNode::Quote { children, .. }
+        | HtmlNode::List { children, .. }
+        | HtmlNode::Heading { children, .. }
+        | HtmlNode::Item(children)
+        | HtmlNode::Bold(children)
+        | HtmlNode::Italics(children)
+        | HtmlNode::Shout(children)
+        | HtmlNode::Styled { children, .. }
+        | HtmlNode::Table { children, .. }
+        | HtmlNode::Row(children)
+        | HtmlNode::Cell(children)
+        | HtmlNode::Container { children, .. } => children.iter().collect(),
+        HtmlNode::Figure { image, caption, .. } => std::iter::once(image.as_ref()).chain(caption.iter()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The text of the nodes without their tags, the text of inline elements and the values of variables are joined by spaces
+fn plain_text(nodes: &[HtmlNode]) -> String {
+    let mut words: Vec<&str> = Vec::new();
+    let mut pending: Vec<&HtmlNode> = nodes.iter().rev().collect();
+    while let Some(node) = pending.pop() {
+        match node {
+            HtmlNode::Text(text) | HtmlNode::Code(text) | HtmlNode::Link { text, .. } => words.push(text),
+            HtmlNode::VarUse { value: Some(value), .. } => words.push(value),
+            _ => pending.extend(node_children(node).into_iter().rev()),
+        }
+    }
+
+    words.retain(|word| !word.is_empty());
+    words.join(" ")
+}
+
+// Block nodes start on their own line in the html output, the others are inline
+fn is_block(node: &HtmlNode) -> bool {
+    !matches!(
+        node,
+        HtmlNode::Bold(_)
+            | HtmlNode::Shout(_)
+            | HtmlNode::Italics(_)
+            | HtmlNode::Newline
+            | HtmlNode::Link { .. }
+            | HtmlNode::Styled { .. }
+            | HtmlNode::Code(_)
+            | HtmlNode::Image { .. }
+            | HtmlNode::Text(_)
+            | HtmlNode::VarUse { .. }
+    )
+}
+
+// A block or element with nothing inside, like #maek paragraf #oic, the elements that never hold anything are not empty
+fn is_empty_element(node: &HtmlNode) -> bool {
+    match node {
+        HtmlNode::Paragraph { children, .. }
+        | HtmlNode::Quote { children, .. }
+        | HtmlNode::List { children, .. }
+        | HtmlNode::Heading { children, .. }
+        | HtmlNode::Table { children, .. }
+        | HtmlNode::Container { children, .. }
+        | HtmlNode::Styled { children, .. }
+        | HtmlNode::Bold(children)
+        | HtmlNode::Italics(children)
+        | HtmlNode::Shout(children) => children.is_empty(),
+        HtmlNode::Code(text) => text.is_empty(),
+        _ => false,
+    }
+}
+
+// The source line of the element of a block node, None for the nodes that do not keep their line
+fn node_line(node: &HtmlNode) -> Option<usize> {
+    match node {
+        HtmlNode::Head { line, .. }
+        | HtmlNode::Paragraph { line, .. }
+        | HtmlNode::Quote { line, .. }
+        | HtmlNode::List { line, .. }
+        | HtmlNode::Heading { line, .. }
+        | HtmlNode::Table { line, .. }
+        | HtmlNode::Figure { line, .. }
+        | HtmlNode::Container { line, .. } => Some(*line),
+        _ => None,
+    }
+}
+
+// Quote a string for JSON, escaping quotes, backslashes and control characters
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Describe a node and its children as a JSON object, the type field names the node
+fn node_json(node: &HtmlNode) -> String {
+    let children = |children: &[HtmlNode]| -> String {
+        children.iter().map(node_json).collect::<Vec<_>>().join(",")
+    };
+    let parts = match node {
+        HtmlNode::Document(nodes) => format!("\"type\":\"document\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Head { line, children: nodes } => format!("\"type\":\"head\",\"line\":{},\"children\":[{}]", line, children(nodes)),
+        HtmlNode::Title(nodes) => format!("\"type\":\"title\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Paragraph { line, children: nodes } => format!("\"type\":\"paragraph\",\"line\":{},\"children\":[{}]", line, children(nodes)),
+        HtmlNode::Quote { line, children: nodes } => format!("\"type\":\"quote\",\"line\":{},\"children\":[{}]", line, children(nodes)),
+        HtmlNode::List { line, children: nodes } => format!("\"type\":\"list\",\"line\":{},\"children\":[{}]", line, children(nodes)),
+        HtmlNode::Heading { level, line, children: nodes } => {
+            format!("\"type\":\"heading\",\"level\":{},\"line\":{},\"children\":[{}]", level, line, children(nodes))
+        }
+        HtmlNode::Item(nodes) => format!("\"type\":\"item\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Bold(nodes) => format!("\"type\":\"bold\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Italics(nodes) => format!("\"type\":\"italics\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Shout(nodes) => format!("\"type\":\"shout\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Newline => "\"type\":\"newline\"".to_string(),
+        HtmlNode::HRule => "\"type\":\"divider\"".to_string(),
+        HtmlNode::Audio(addresses) => format!(
+            "\"type\":\"audio\",\"addresses\":[{}]",
+            addresses.iter().map(|address| json_string(address)).collect::<Vec<_>>().join(",")
+        ),
+        HtmlNode::Video(address) => format!("\"type\":\"video\",\"address\":{}", json_string(address)),
+        HtmlNode::Link { address, text } => format!("\"type\":\"link\",\"address\":{},\"text\":{}", json_string(address), json_string(text)),
+        HtmlNode::Text(text) => format!("\"type\":\"text\",\"text\":{}", json_string(text)),
+        HtmlNode::VarUse { name, value, line } => format!(
+            "\"type\":\"variable_use\",\"name\":{},\"value\":{},\"line\":{}",
+            json_string(name),
+            value.as_deref().map_or("null".to_string(), json_string),
+            line
+        ),
+        HtmlNode::Comment(text) => format!("\"type\":\"comment\",\"text\":{}", json_string(text)),
+        HtmlNode::Description(text) => format!("\"type\":\"description\",\"text\":{}", json_string(text)),
+        HtmlNode::Styled { class, children: nodes } => format!("\"type\":\"styled\",\"class\":{},\"children\":[{}]", json_string(class), children(nodes)),
+        HtmlNode::Stylesheet(address) => format!("\"type\":\"stylesheet\",\"address\":{}", json_string(address)),
+        HtmlNode::Table { line, children: nodes } => format!("\"type\":\"table\",\"line\":{},\"children\":[{}]", line, children(nodes)),
+        HtmlNode::Row(nodes) => format!("\"type\":\"row\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Cell(nodes) => format!("\"type\":\"cell\",\"children\":[{}]", children(nodes)),
+        HtmlNode::Code(code) => format!("\"type\":\"code\",\"text\":{}", json_string(code)),
+        HtmlNode::Image { address, alt } => format!("\"type\":\"image\",\"address\":{},\"alt\":{}", json_string(address), json_string(alt)),
+        HtmlNode::Container { line, class, children: nodes } => format!(
+            "\"type\":\"container\",\"line\":{},\"class\":{},\"children\":[{}]",
+            line,
+            class.as_deref().map_or("null".to_string(), json_string),
+            children(nodes)
+        ),
+        HtmlNode::Figure { line, image, caption } => format!(
+            "\"type\":\"figure\",\"line\":{},\"image\":{},\"caption\":[{}]",
+            line,
+            node_json(image),
+            children(caption)
+        ),
+    };
+    format!("{{{}}}", parts)
+}
+
+// Get the MIME type of a video address from its extension, defaults to video/mp4
+fn video_mime_type(address: &str) -> &'static str {
+    let extension = address
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.rsplit_once('.'))
+        .map(|(_name, extension)| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("webm") => "video/webm",
+        Some("ogv") | Some("ogg") => "video/ogg",
+        Some("mov") => "video/quicktime",
+        _ => "video/mp4",
+    }
+}
+
+// Encode bytes as base64 with padding, used for the data URIs of --inline-media
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// Get the MIME type of inlined media from the element the address was found in, an .ogg can be audio or video
+// Images are not inlined, they have no media MIME type
+fn media_mime_type(address: &str, kind: AssetKind) -> Option<&'static str> {
+    match kind {
+        AssetKind::Audio => Some(audio_mime_type(address)),
+        AssetKind::Video => Some(video_mime_type(address)),
+        AssetKind::Image => None,
+    }
+}
+
+// Get the MIME type of an audio address from its extension, defaults to audio/mpeg
+fn audio_mime_type(address: &str) -> &'static str {
+    let extension = address
+        .rsplit('/')
+        .next()
+        .and_then(|file| file.rsplit_once('.'))
+        .map(|(_name, extension)| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("ogg") | Some("oga") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("m4a") => "audio/mp4",
+        Some("aac") => "audio/aac",
+        Some("flac") => "audio/flac",
+        Some("webm") => "audio/webm",
+        _ => "audio/mpeg",
+    }
+}
+
+impl Default for LolcodeCompiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Implementation for lolcode compiler
+impl LolcodeCompiler {
+    pub fn new() -> Self {
+        Self {
+            lexer: LolcodeLexicalAnalyzer::new(""),
+            parser: LolcodeSyntaxAnalyzer::new(),
+            current_tok: String::new(),
+            scope_stack: vec![HashMap::new()],
+            language_tokens: vec![],
+            tag_map: TagMap::new(),
+            shorthand: false,
+            source_comments: false,
+            allowed_schemes: DEFAULT_ALLOWED_SCHEMES.iter().map(|scheme| scheme.to_string()).collect(),
+            strip_comments: false,
+            asset_urls: HashMap::new(),
+            pedantic: false,
+            document: None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            current_line: 1,
+            current_column: 1,
+            lang: "en".to_string(),
+            strict: false,
+            fragment: false,
+            keywords: KeywordConfig::default(),
+            allow_shadowing: true,
+            minify: false,
+            declarations: Vec::new(),
+            source_map: RefCell::new(Vec::new()),
+            max_depth: DEFAULT_MAX_DEPTH,
+            pedantic_notes: Vec::new(),
+            unused_variables: Vec::new(),
+            annotate: false,
+            img_responsive: false,
+            default_title: String::new(),
+        }
+    }
+
+    // Get the first token and validate if it is empty
+    fn start(&mut self) {
+        // Get the first token
+        self.current_tok = self.next_token();
+
+        // Record an error if the file is blank, only whitespace gives no tokens
+        if self.current_tok.is_empty() {
+            self.errors.push(CompileError::Structure {
+                line: 1,
+                message: "The file is blank, a document starts with #hai and ends with #kthxbye.".to_string(),
+            });
+        }
+        // Record an error if every token is inside a comment, the document itself is missing
+        else if self.only_comments() {
+            self.errors.push(CompileError::Structure {
+                line: 1,
+                message: "The file only contains comments, a document starts with #hai and ends with #kthxbye.".to_string(),
+            });
+        }
+    }
+
+    // Check whether every token of the file is part of a #obtw ... #tldr comment, nested comments included
+    fn only_comments(&self) -> bool {
+        let mut depth = 0;
+        for (token, _line, _column) in self.lexer.tokens.iter() {
+            let lower = token.to_lowercase();
+            if self.lexer.comment_start.contains(&lower) {
+                depth += 1;
+            } else if depth == 0 {
+                return false;
+            } else if self.lexer.comment_end.contains(&lower) {
+                depth -= 1;
+            }
+        }
+        true
+    }
+
+    // Parse the lolcode document
+    fn lolcode(&mut self) {
+        // Document should start with #HAI, if not record an error and parse the rest as the document
+        if !self
+            .lexer
+            .head_start
+            .iter()
+            .any(|h| h == &self.current_tok.to_lowercase())
+        {
+            self.errors.push(syntax_error(self.token_line(), self.token_column(), &["#hai"], &self.current_tok));
+        } else {
+            // get the next token from the compiler
+            self.current_tok = self.next_token();
+        }
+
+        // Parse the document structure
+        //Initialize the parser
+        let mut parser = std::mem::take(&mut self.parser);
+
+        // Parse the lolcode document with parser
+        let document = parser.parse_lolcode(self);
+
+        //Assign the parser to the object
+        self.parser = parser;
+
+        // Document should end with #KTHXBYE, record an error if #kthxbye not found at the end
+        // An unterminated block already says the input ended, the missing #kthxbye is not reported again
+        let reported_end = self.current_tok.is_empty()
+            && self.errors.iter().any(|error| matches!(error, CompileError::Unterminated { .. }));
+        if !reported_end
+            && !self
+                .lexer
+                .head_end
+                .iter()
+                .any(|h| h == &self.current_tok.to_lowercase())
+        {
+            self.errors.push(syntax_error(self.token_line(), self.token_column(), &["#kthxbye"], &self.current_tok));
+        }
+
+        // A syntax error at a shorthand alias names the alias as it was written, not the keyword it stands for
+        for error in self.errors.iter_mut() {
+            if let CompileError::Syntax { line, column, found, .. } = error {
+                let written = self.lexer.tokens.iter().find(|(_token, token_line, token_column)| token_line == line && token_column == column);
+                if let Some((alias, _line, _column)) = written.filter(|(token, _line, _column)| self.lexer.shorthand.contains_key(&token.to_lowercase())) {
+                    *found = alias.clone();
+                }
+            }
+        }
+
+        // The document scope ends with the document, warn about its unused variables
+        let scopes = std::mem::take(&mut self.scope_stack);
+        for scope in scopes.iter().rev() {
+            self.warn_unused_variables(scope);
+        }
+        self.scope_stack = scopes;
+
+        // Keep the document tree for HTML conversion
+        self.document = Some(document);
+    }
+
+    /*****
+     * Task 3 - Semantic Analyzer part - Static variable scope resolution methods
+     * 1. Push scope - Push the new variable scope into the stack
+     * 2. Pop scope - Remove the new variable scope from the stack
+     * 3. Declare variable - semantic analyzer validating variable re-declaration and assignment to scope
+     * 4. lookup - function supporting retrieval of the values of variable defined in the scopre
+     */
+
+    // Push the new scope to the stack
+    fn push_scope(&mut self) {
+        self.scope_stack.push(HashMap::new());
+
+    }
+
+    // Pop the scope from the stack if there are more than one stack, one scope for global variables 
+    fn pop_scope(&mut self) {
+        if self.scope_stack.len() > 1 {
+            if let Some(scope) = self.scope_stack.pop() {
+                self.warn_unused_variables(&scope);
+            }
+        }
+    }
+
+    // Record a warning for every variable of a scope that was never used, in the order they were defined
+    fn warn_unused_variables(&mut self, scope: &HashMap<String, VariableInfo>) {
+        let mut unused: Vec<&VariableInfo> = scope.values().filter(|variable| !variable.used).collect();
+        unused.sort_by_key(|variable| variable.line_defined);
+
+        for variable in unused {
+            self.warnings.push(format!(
+                "variable '{}' defined at line {} is never used.",
+                variable.name, variable.line_defined
+            ));
+            self.unused_variables.push((variable.name.clone(), variable.line_defined));
+        }
+    }
+
+    // Declare a variable in the current scope with semantic analysis to validate for re-declaration and insert it into scope stack
+    fn declare_variable(&mut self, name: String, value: Option<String>, kind: VarKind, line: usize) {
+
+        //The document scope is never popped, if the stack is empty anyway restore it so the declaration is not lost and report the broken invariant
+        if self.scope_stack.is_empty() {
+            self.warnings.push(format!(
+                "internal error: no variable scope when '{}' was declared at line {}, the document scope was restored.",
+                name, line
+            ));
+            self.scope_stack.push(HashMap::new());
+        }
+
+        //Element keywords and the words of the variable tags are reserved, a variable named after one would be read as the keyword
+        if matches!(
+            self.lexer.classify_token(&name),
+            Some(TokenKind::Element | TokenKind::VariableStart | TokenKind::VariableMid | TokenKind::VariableUse)
+        ) {
+            self.errors.push(CompileError::Semantic {
+                line,
+                message: format!("'{}' is a reserved keyword and cannot be a variable name.", name),
+            });
+            return;
+        }
+       
+       //Check if there is any variable with the same name in the current scope, if so record an error and keep the first definition
+        if let Some(current_scope) = self.scope_stack.last_mut() {
+            if current_scope.contains_key(&name) {
+                let existing = &current_scope[&name];
+                self.errors.push(CompileError::Semantic {
+                    line,
+                    message: format!(
+                        "Variable '{}' is already defined at line {} in the current scope.",
+                        name, existing.line_defined
+                    ),
+                });
+                return;
+            }
+        }
+
+        //Without shadowing, a variable of an outer scope cannot be declared again either
+        if !self.allow_shadowing {
+            let outer_scopes = &self.scope_stack[..self.scope_stack.len().saturating_sub(1)];
+            if let Some(existing) = outer_scopes.iter().rev().find_map(|scope| scope.get(&name)) {
+                self.errors.push(CompileError::Semantic {
+                    line,
+                    message: format!(
+                        "Variable '{}' declared at line {} shadows the variable defined at line {} in an outer scope.",
+                        name, line, existing.line_defined
+                    ),
+                });
+                return;
+            }
+        }
+
+        //Log the declaration with the depth of its scope, the document scope is 0
+        self.declarations.push((name.clone(), value.clone(), line, self.scope_stack.len().saturating_sub(1)));
+
+        if let Some(current_scope) = self.scope_stack.last_mut() {
+            //Validation complete, insert the variable into the current scope
+            current_scope.insert(
+                name.clone(),
+                VariableInfo {
+                    name,
+                    value,
+                    line_defined: line,
+                    used: false,
+                    kind,
+                },
+            );
+
+        }
+            
+
+    }
+
+    // Validate the shape of an audio or video address, the address regex only restricts its characters
+    // http:// and https:// addresses need a host with a dot, other schemes are left to check_url_scheme, anything else must be a relative path
+    fn check_media_address(&mut self, url: &str, line: usize) {
+        let scheme_rule = Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*:").unwrap();
+        let relative_rule = Regex::new(r"^[A-Za-z0-9_\-%./]*[A-Za-z0-9][A-Za-z0-9_\-%./]*$").unwrap();
+
+        let lower = url.to_lowercase();
+        let valid = match lower.strip_prefix("http://").or_else(|| lower.strip_prefix("https://")) {
+            Some(rest) => {
+                let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+                host.contains('.') && !host.starts_with('.') && !host.ends_with('.')
+            }
+            None => scheme_rule.is_match(url) || relative_rule.is_match(url),
+        };
+
+        // Record an error with the offending address
+        if !valid {
+            self.errors.push(CompileError::Semantic {
+                line,
+                message: format!(
+                    "'{}' is not a valid media address, use an http:// or https:// address with a host, or a relative path.",
+                    url
+                ),
+            });
+        }
+    }
+
+    // Validate the scheme of a URL against the allowed schemes, addresses without a scheme are relative and always allowed
+    fn check_url_scheme(&mut self, url: &str, line: usize) {
+        let scheme_rule = Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*$").unwrap();
+
+        // Text before the first ':' is the scheme, if it looks like one
+        let Some((scheme, _rest)) = url.split_once(':') else {
+            return;
+        };
+        if !scheme_rule.is_match(scheme) {
+            return;
+        }
+
+        // Record an error with the offending URL if the scheme is not allowed
+        let scheme = scheme.to_lowercase();
+        if !self.allowed_schemes.iter().any(|allowed| allowed == &scheme) {
+            self.errors.push(CompileError::Semantic {
+                line,
+                message: format!(
+                    "URL '{}' uses scheme '{}:' which is not allowed (allowed: {}).",
+                    url,
+                    scheme,
+                    self.allowed_schemes.join(", ")
+                ),
+            });
+        }
+    }
+
+    // Run the whole pipeline on a program string - lexing, parsing, semantic checks, the --pedantic checks if enabled and html conversion
+    // Returns every error found, the document is not parsed if the lexer found invalid tokens
+    pub fn compile_to_html(&mut self, source: &str) -> Result<String, Vec<CompileError>> {
+        self.check(source)?;
+        self.render_output().map_err(|error| vec![error])
+    }
+
+    // Run the pipeline of compile_to_html without the html conversion, the parsed document is kept for rendering
+    pub fn check(&mut self, source: &str) -> Result<(), Vec<CompileError>> {
+        self.compile(source);
+        if self.errors.is_empty() {
+            self.parse();
+        }
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        if self.pedantic {
+            let errors = self.pedantic_errors();
+            if !errors.is_empty() {
+                return Err(errors);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Convert the document the way the output options ask for, the whole page or only the body content, minified if requested
+    fn render_output(&self) -> Result<String, CompileError> {
+        let html_string = if self.fragment {
+            self.to_html_fragment()?
+        } else {
+            self.to_html()?
+        };
+
+        if self.minify {
+            Ok(minify_html(&html_string, self.tag_map.tag("codez")))
+        } else {
+            Ok(html_string)
+        }
+    }
+
+    // Find the discouraged patterns rejected by --pedantic after a successful parse
+    // The parser noted the empty blocks and repeated newlines and the unused variables, only the tags are checked here
+    // A declaration with half of its keyword (haz without #i) is always a syntax error, it cannot reach this check
+    fn pedantic_errors(&self) -> Vec<CompileError> {
+        let mut errors = Vec::new();
+
+        // Tags must be written in lowercase, a word of text is never a tag
+        for (token, line, _column) in self.language_tokens.iter() {
+            let lower = token.to_lowercase();
+            if is_tag(token) && token != &lower {
+                errors.push(CompileError::Pedantic {
+                    line: *line,
+                    message: format!("tag '{}' should be written in lowercase as '{}'.", token, lower),
+                });
+            }
+        }
+
+        for (line, message) in &self.pedantic_notes {
+            errors.push(CompileError::Pedantic { line: *line, message: message.clone() });
+        }
+
+        // Declared variables that are never used
+        for (name, line) in &self.unused_variables {
+            errors.push(CompileError::Pedantic {
+                line: *line,
+                message: format!("variable '{}' is declared but never used.", name),
+            });
+        }
+
+        errors
+    }
+
+    // Return the local (relative) audio, video and image addresses of the document with the kind of their element, in order and without duplicates
+    // The addresses come from the document tree, a word like soundz in the text of a paragraph is not an element
+    fn local_assets(&self) -> Vec<(String, AssetKind)> {
+        let scheme_rule = Regex::new(r"^[A-Za-z][A-Za-z0-9+.\-]*:").unwrap();
+        let mut assets: Vec<(String, AssetKind)> = Vec::new();
+
+        //Walk the tree with a stack of the nodes left, the children are pushed last first so they are taken in document order
+        let mut pending: Vec<&HtmlNode> = self.document.iter().collect();
+        while let Some(node) = pending.pop() {
+            let addresses: Vec<(&String, AssetKind)> = match node {
+                HtmlNode::Audio(addresses) => addresses.iter().map(|address| (address, AssetKind::Audio)).collect(),
+                HtmlNode::Video(address) => vec![(address, AssetKind::Video)],
+                HtmlNode::Image { address, .. } => vec![(address, AssetKind::Image)],
+                _ => Vec::new(),
+            };
+
+            for (address, kind) in addresses {
+                if !scheme_rule.is_match(address)
+                    && !address.starts_with("//")
+                    && !assets.iter().any(|(asset, _kind)| asset == address)
+                {
+                    assets.push((address.clone(), kind));
+                }
+            }
+
+            pending.extend(node_children(node).into_iter().rev());
+        }
+
+        assets
+    }
+
+    // Write the local audio and video of the document as data URIs, the addresses are resolved against the directory of the .lol file
+    // A file that cannot be read or is larger than INLINE_MEDIA_LIMIT keeps its address, with a warning that says why
+    fn inline_media(&mut self, source_dir: &Path) {
+        for (address, kind) in self.local_assets() {
+            let Some(mime_type) = media_mime_type(&address, kind) else {
+                continue;
+            };
+            let media_path = source_dir.join(&address);
+
+            //Check the size before reading, a large video would make the page many times its size
+            let contents = fs::metadata(&media_path).and_then(|metadata| {
+                if metadata.len() > INLINE_MEDIA_LIMIT {
+                    Ok(Err(metadata.len()))
+                } else {
+                    fs::read(&media_path).map(Ok)
+                }
+            });
+
+            match contents {
+                Ok(Ok(bytes)) => {
+                    self.asset_urls.insert(address, format!("data:{};base64,{}", mime_type, base64_encode(&bytes)));
+                }
+                Ok(Err(size)) => self.warnings.push(format!(
+                    "media '{}' is not inlined, it is {} bytes and only files up to {} bytes are inlined.",
+                    address, size, INLINE_MEDIA_LIMIT
+                )),
+                Err(e) => self.warnings.push(format!("media '{}' is not inlined, {}", address, read_error_message(&e, &media_path))),
+            }
+        }
+    }
+
+    // Return the address an asset is written as in the output, rewritten when it is bundled
+    fn asset_url(&self, address: &str) -> String {
+        self.asset_urls
+            .get(address)
+            .cloned()
+            .unwrap_or_else(|| address.to_string())
+    }
+
+    // Line of the current token, set by next_token every time a token is consumed
+    fn token_line(&self) -> usize {
+        self.current_line
+    }
+
+    // The token after the current one, without consuming it, empty at the end of input
+    fn peek_token(&self) -> String {
+        self.lexer.tokens.peek().map(|(token, _line, _column)| token.clone()).unwrap_or_default()
+    }
+
+    // Column of the current token, kept next to its line
+    fn token_column(&self) -> usize {
+        self.current_column
+    }
+
+    //Function to retrieve values of the variables, retrieves the value from the innermost scope for a variable
+    fn lookup_variable(&mut self, name: &str) -> Option<&mut VariableInfo> {
+        // Search from innermost to outermost scope, switch to outerscope if value not found in local scope
+        for scope in self.scope_stack.iter_mut().rev() {
+            if let Some(var_info) = scope.get_mut(name) {
+                return Some(var_info);
+            }
+        }
+        //return None if value not found
+        None
+    }
+
+    /**
+     * Task 4 - HTML Conversion - convert the syntactically and semantically valid lolcode into HTML
+     */
+    fn to_html(&self) -> Result<String, CompileError> {
+
+        //Initialize an empty html string
+        let mut html_string: String = String::new();
+
+        // Render the document tree built by the parser, already validated
+        if let Some(document) = &self.document {
+            self.render_node(document, 0, &mut html_string)?;
+        }
+
+        //return html string
+        Ok(html_string)
+
+    }
+
+    // Convert the document into HTML along with the byte range of the html of every block and the source line of the block
+    // The ranges are in the order they start in the html, a block inside another comes after it
+    pub fn to_html_with_map(&mut self) -> Result<(String, SourceMap), CompileError> {
+        self.source_map.get_mut().clear();
+        let html_string = self.to_html()?;
+
+        let mut source_map = std::mem::take(self.source_map.get_mut());
+        source_map.sort_by_key(|(range, _line)| range.start);
+
+        Ok((html_string, source_map))
+    }
+
+    // Convert only the body content of the document, the doctype, html and head are left out so it can be embedded in an existing page
+    pub fn to_html_fragment(&self) -> Result<String, CompileError> {
+        let mut html_string: String = String::new();
+
+        if let Some(HtmlNode::Document(children)) = &self.document {
+            for child in children {
+                if !matches!(child, HtmlNode::Head { .. }) {
+                    self.render_node(child, 0, &mut html_string)?;
+                }
+            }
+        }
+
+        Ok(html_string)
+    }
+
+    // Every variable declared in the document as its name, value and line, in the order they were declared
+    // Taken from the declaration log, the scopes of the paragraphs are already popped once the document is parsed
+    pub fn declared_variables(&self) -> Vec<(String, Option<String>, usize)> {
+        self.declarations
+            .iter()
+            .map(|(name, value, line, _scope)| (name.clone(), value.clone(), *line))
+            .collect()
+    }
+
+    // Describe the parsed document as JSON for other tools - the title, the document tree and the declared variables with their scopes and lines
+    pub fn to_json(&self) -> String {
+        let title = self.title_text().map_or("null".to_string(), |title| json_string(&title));
+        let document = self.document.as_ref().map_or("null".to_string(), node_json);
+        let variables: Vec<String> = self
+            .declarations
+            .iter()
+            .map(|(name, value, line, scope)| {
+                format!(
+                    "{{\"name\":{},\"value\":{},\"line\":{},\"scope\":{}}}",
+                    json_string(name),
+                    value.as_deref().map_or("null".to_string(), json_string),
+                    line,
+                    scope
+                )
+            })
+            .collect();
+
+        format!("{{\"title\":{},\"document\":{},\"variables\":[{}]}}\n", title, document, variables.join(","))
+    }
+
+    // The text of the title of the head, variable uses are written as their values
+    fn title_text(&self) -> Option<String> {
+        let Some(HtmlNode::Document(children)) = &self.document else {
+            return None;
+        };
+        children.iter().find_map(|child| match child {
+            HtmlNode::Head { children, .. } => children.iter().find_map(|child| match child {
+                HtmlNode::Title(parts) => Some(
+                    parts
+                        .iter()
+                        .filter_map(|part| match part {
+                            HtmlNode::Text(text) => Some(text.clone()),
+                            HtmlNode::VarUse { value, .. } => value.clone().filter(|value| !value.is_empty()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => None,
+            }),
+            _ => None,
+        })
+    }
+
+    // The title written in the head, the text of the first heading if the title is left out or empty, otherwise the --default-title
+    fn page_title(&self) -> String {
+        if let Some(title) = self.title_text().filter(|title| !title.is_empty()) {
+            return title;
+        }
+
+        //Walk the tree in document order to the first heading with text
+        let mut pending: Vec<&HtmlNode> = self.document.iter().collect();
+        while let Some(node) = pending.pop() {
+            if let HtmlNode::Heading { children, .. } = node {
+                let text = plain_text(children);
+                if !text.is_empty() {
+                    return text;
+                }
+            }
+            pending.extend(node_children(node).into_iter().rev());
+        }
+
+        self.default_title.clone()
+    }
+
+    // Source comment line that marks the line of the block that follows, empty unless --source-comments or --annotate is used
+    fn source_comment(&self, line: usize, depth: usize) -> String {
+        if self.annotate {
+            format!("{}<!-- line {} -->\n", indent(depth), line)
+        } else if self.source_comments {
+            format!("{}<!-- src:{} -->\n", indent(depth), line)
+        } else {
+            String::new()
+        }
+    }
+
+    // Append an element on its own lines, inline children stay on the line of their tags
+    // If it has block children, every block child and every run of inline children gets its own line, indented one level deeper
+    fn render_element(&self, tag: &str, open: &str, children: &[HtmlNode], depth: usize, html_string: &mut String) -> Result<(), CompileError> {
+        if !children.iter().any(is_block) {
+            html_string.push_str(&format!("{}<{}>{}</{}>\n", indent(depth), open, self.render_inline(children)?, tag));
+            return Ok(());
+        }
+
+        html_string.push_str(&format!("{}<{}>\n", indent(depth), open));
+
+        let mut run_start = 0;
+        for (index, child) in children.iter().enumerate() {
+            if is_block(child) {
+                self.render_inline_line(&children[run_start..index], depth + 1, html_string)?;
+                self.render_node(child, depth + 1, html_string)?;
+                run_start = index + 1;
+            }
+        }
+        self.render_inline_line(&children[run_start..], depth + 1, html_string)?;
+
+        html_string.push_str(&format!("{}</{}>\n", indent(depth), tag));
+        Ok(())
+    }
+
+    // Append a run of inline nodes as one indented line, nothing if the run is empty
+    fn render_inline_line(&self, nodes: &[HtmlNode], depth: usize, html_string: &mut String) -> Result<(), CompileError> {
+        if !nodes.is_empty() {
+            html_string.push_str(&format!("{}{}\n", indent(depth), self.render_inline(nodes)?));
+        }
+        Ok(())
+    }
+
+    // Render inline nodes separated by single spaces
+    fn render_inline(&self, nodes: &[HtmlNode]) -> Result<String, CompileError> {
+        let mut parts = Vec::new();
+        for node in nodes {
+            //A variable without a value renders nothing, it gets no space of its own
+            let part = self.render_inline_node(node)?;
+            if !part.is_empty() {
+                parts.push(part);
+            }
+        }
+        Ok(parts.join(" "))
+    }
+
+    // Render an inline node, report a variable use that has no value
+    fn render_inline_node(&self, node: &HtmlNode) -> Result<String, CompileError> {
+
+        // Get the tag map to look up the output tag of each element
+        let tags = &self.tag_map;
+
+        match node {
+            HtmlNode::Bold(children) => {
+                Ok(format!("<{}>{}</{}>", tags.tag("bold"), self.render_inline(children)?, tags.tag("bold")))
+            }
+
+            HtmlNode::Italics(children) => {
+                Ok(format!("<{}>{}</{}>", tags.tag("italics"), self.render_inline(children)?, tags.tag("italics")))
+            }
+
+            //Shout is strong emphasis, the em inside is not part of the tag map
+            HtmlNode::Shout(children) => {
+                Ok(format!("<{}><em>{}</em></{}>", tags.tag("shout"), self.render_inline(children)?, tags.tag("shout")))
+            }
+
+            HtmlNode::Newline => Ok(format!("<{}/>", tags.tag("newline"))),
+
+            HtmlNode::Styled { class, children } => Ok(format!(
+                "<{} class=\"{}\">{}</{}>",
+                tags.tag("stylez"), escape_html(class), self.render_inline(children)?, tags.tag("stylez")
+            )),
+
+            HtmlNode::Link { address, text } => Ok(format!(
+                "<{} href=\"{}\">{}</{}>",
+                tags.tag("linkz"), escape_html(address), escape_html(text), tags.tag("linkz")
+            )),
+
+            //A responsive image shrinks to the width of the page and keeps its proportions, images have no width or height of their own
+            HtmlNode::Image { address, alt } => Ok(format!(
+                "<{} src=\"{}\" alt=\"{}\"{}/>",
+                tags.tag("piccy"),
+                escape_html(&self.asset_url(address)),
+                escape_html(alt),
+                if self.img_responsive { " style=\"max-width:100%;height:auto\"" } else { "" }
+            )),
+
+            HtmlNode::Code(code) => Ok(format!("<{}>{}</{}>", tags.tag("codez"), escape_html(code), tags.tag("codez"))),
+
+            HtmlNode::Text(text) => Ok(escape_html(text)),
+
+            //The value of the variable, report an error if it cannot be resolved
+            HtmlNode::VarUse { name, value, line } => {
+                let value = value.as_ref().ok_or_else(|| CompileError::UnresolvedVariable {
+                    line: *line,
+                    name: name.clone(),
+                })?;
+                Ok(escape_html(value))
+            }
+
+            //Block nodes render on their own line
+            //The block is rendered apart from the output, so the ranges it adds to the source map are dropped
+            _ => {
+                let mapped = self.source_map.borrow().len();
+                let mut block = String::new();
+                self.render_node(node, 0, &mut block)?;
+                self.source_map.borrow_mut().truncate(mapped);
+                Ok(block.trim_end().to_string())
+            }
+        }
+    }
+
+    // Append the HTML of a node and its children at the given depth
+    fn render_node(&self, node: &HtmlNode, depth: usize, html_string: &mut String) -> Result<(), CompileError> {
+
+        // Get the tag map to look up the output tag of each element
+        let tags = &self.tag_map;
+
+        // Remember where the html of the node starts for the source map
+        let start = html_string.len();
+
+        match node {
+            // Append DOCTYPE and html tags around the document
+            // The comments before the head and the head stay under html, the content after the head is wrapped in body
+            HtmlNode::Document(children) => {
+                html_string.push_str("<!DOCTYPE html>\n");
+                html_string.push_str(&format!("{}<html lang=\"{}\">\n", indent(depth), escape_html(&self.lang)));
+                let body_start = children
+                    .iter()
+                    .position(|child| matches!(child, HtmlNode::Head { .. }))
+                    .map_or(0, |head| head + 1);
+                for child in &children[..body_start] {
+                    self.render_node(child, depth + 1, html_string)?;
+                }
+                self.render_element("body", "body", &children[body_start..], depth + 1, html_string)?;
+                html_string.push_str(&format!("{}</html>\n", indent(depth)));
+            }
+
+            //The charset meta is always the first child of the head
+            //A missing title follows the charset, a title without text is written where it was, both with the fallback title
+            HtmlNode::Head { line, children } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                html_string.push_str(&format!("{}<{}>\n", indent(depth), tags.tag("head")));
+                html_string.push_str(&format!("{}<meta charset=\"utf-8\">\n", indent(depth + 1)));
+
+                let fallback_title = match self.title_text() {
+                    Some(title) if !title.is_empty() => None,
+                    _ => Some(format!("{}<{}>{}</{}>\n", indent(depth + 1), tags.tag("title"), escape_html(&self.page_title()), tags.tag("title"))),
+                };
+                if !children.iter().any(|child| matches!(child, HtmlNode::Title(_))) {
+                    html_string.push_str(fallback_title.as_deref().unwrap_or_default());
+                }
+                for child in children {
+                    match (child, &fallback_title) {
+                        (HtmlNode::Title(_), Some(title)) => html_string.push_str(title),
+                        _ => self.render_node(child, depth + 1, html_string)?,
+                    }
+                }
+                html_string.push_str(&format!("{}</{}>\n", indent(depth), tags.tag("head")));
+            }
+
+            HtmlNode::Title(children) => {
+                self.render_element(tags.tag("title"), tags.tag("title"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Paragraph { line, children } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tags.tag("paragraf"), tags.tag("paragraf"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Quote { line, children } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tags.tag("saidz"), tags.tag("saidz"), children, depth, html_string)?;
+            }
+
+            HtmlNode::List { line, children } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tags.tag("list"), tags.tag("list"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Heading { level, line, children } => {
+                let tag = tags.tag(HEADING_ELEMENTS[level - 1]);
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tag, tag, children, depth, html_string)?;
+            }
+
+            HtmlNode::Item(children) => {
+                self.render_element(tags.tag("item"), tags.tag("item"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Table { line, children } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tags.tag("grid"), tags.tag("grid"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Row(children) => {
+                self.render_element(tags.tag("roe"), tags.tag("roe"), children, depth, html_string)?;
+            }
+
+            HtmlNode::Container { line, class, children } => {
+                let open = match class {
+                    Some(class) => format!("{} class=\"{}\"", tags.tag("boxz"), escape_html(class)),
+                    None => tags.tag("boxz").to_string(),
+                };
+                html_string.push_str(&self.source_comment(*line, depth));
+                self.render_element(tags.tag("boxz"), &open, children, depth, html_string)?;
+            }
+
+            //The image of a figure is followed by its caption, if it has one
+            HtmlNode::Figure { line, image, caption } => {
+                html_string.push_str(&self.source_comment(*line, depth));
+                html_string.push_str(&format!("{}<{}>\n", indent(depth), tags.tag("showcase")));
+                self.render_node(image, depth + 1, html_string)?;
+                if !caption.is_empty() {
+                    self.render_element("figcaption", "figcaption", caption, depth + 1, html_string)?;
+                }
+                html_string.push_str(&format!("{}</{}>\n", indent(depth), tags.tag("showcase")));
+            }
+
+            HtmlNode::Cell(children) => {
+                self.render_element(tags.tag("sel"), tags.tag("sel"), children, depth, html_string)?;
+            }
+
+            //Append a source for every address, typed by its extension
+            HtmlNode::Audio(addresses) => {
+                html_string.push_str(&format!("{}<{} controls>\n", indent(depth), tags.tag("soundz")));
+                for address in addresses {
+                    html_string.push_str(&format!(
+                        "{}<source src=\"{}\" type=\"{}\">\n",
+                        indent(depth + 1),
+                        escape_html(&self.asset_url(address)),
+                        audio_mime_type(address)
+                    ));
+                }
+                html_string.push_str(&format!("{}</{}>\n", indent(depth), tags.tag("soundz")));
+            }
+
+            HtmlNode::Video(address) => {
+                html_string.push_str(&format!(
+                    "{}<{} src=\"{}\"></{}>\n",
+                    indent(depth), tags.tag("vidz"), escape_html(&self.asset_url(address)), tags.tag("vidz")
+                ));
+            }
+
+            HtmlNode::HRule => {
+                html_string.push_str(&format!("{}<{}/>\n", indent(depth), tags.tag("divider")));
+            }
+
+            HtmlNode::Stylesheet(address) => {
+                html_string.push_str(&format!("{}<link rel=\"stylesheet\" href=\"{}\">\n", indent(depth), escape_html(address)));
+            }
+
+            HtmlNode::Description(text) => {
+                html_string.push_str(&format!("{}<meta name=\"description\" content=\"{}\">\n", indent(depth), escape_html(text)));
+            }
+
+            //Comments are only appended when the comment policy keeps them
+            HtmlNode::Comment(text) => {
+                if !self.strip_comments {
+                    if text.is_empty() {
+                        html_string.push_str(&format!("{}<!-- -->\n", indent(depth)));
+                    } else {
+                        html_string.push_str(&format!("{}<!-- {} -->\n", indent(depth), escape_html(text)));
+                    }
+                }
+            }
+
+            //Inline nodes outside of a block get a line of their own
+            _ => self.render_inline_line(std::slice::from_ref(node), depth, html_string)?,
+        }
+
+        // Map the html of the node to the line of its element, only the blocks know their line
+        if let Some(line) = node_line(node) {
+            self.source_map.borrow_mut().push((start..html_string.len(), line));
+        }
+
+        Ok(())
+    }
+
+}
+
+// Implementation for the LolCodeCompiler
+impl Compiler for LolcodeCompiler {
+
+    //method to start tokenization and getting first token
+    fn compile(&mut self, source: &str) {
+
+        //Initialize a lexer
+        self.lexer = LolcodeLexicalAnalyzer::with_keywords(source, self.keywords.clone());
+
+        //Accept the compact keywords if shorthand mode is enabled
+        if self.shorthand {
+            self.lexer.enable_shorthand(DEFAULT_SHORTHAND);
+        }
+
+        //Explain unknown tags if strict mode is enabled
+        self.lexer.strict = self.strict;
+
+        //Tokenize the lexer into tokens
+        self.lexer.tokenize();
+
+        //Get language tokens - used later for HTML conversion, shorthand aliases are written as their regular keywords
+        self.language_tokens = self.lexer.canonical_tokens();
+
+        //Record every token that is not a lexeme, the document is not parsed if there is one
+        for (token, line, column) in self.lexer.tokens.iter() {
+            if !self.lexer.lookup(token) {
+                //In strict mode an unknown tag lists the tags that are valid
+                if self.lexer.strict && is_tag(token) {
+                    self.errors.push(CompileError::UnknownTag {
+                        line: *line,
+                        column: *column,
+                        token: token.clone(),
+                        valid: self.lexer.tag_keywords(),
+                    });
+                } else {
+                    self.errors.push(CompileError::Lexical {
+                        line: *line,
+                        column: *column,
+                        token: token.clone(),
+                        suggestion: self.lexer.closest_tag(token),
+                    });
+                }
+            }
+        }
+
+        //Get the first input token 
+        if self.errors.is_empty() {
+            self.start();
+        }
+    }
+
+    //method to lexically analyzer a token
+    fn next_token(&mut self) -> String {
+
+        //Take the next token from the stream
+        let result = self.lexer.tokens.next_token();
+
+
+        //Return a lexeme and its line if it is valid, else record an error and end the input there
+        if let Some((candidate, line, column)) = result {
+            self.current_line = line;
+            self.current_column = column;
+
+            if self.lexer.lookup(&candidate) {
+                self.current_tok = candidate.clone();
+                candidate
+            } else {
+                let suggestion = self.lexer.closest_tag(&candidate);
+                self.errors.push(CompileError::Lexical { line, column, token: candidate, suggestion });
+                self.current_tok.clear();
+                String::new()
+            }
+        } 
+        //nothing found, errors at the end of input are reported where the last token ends, clear current token and initialize new string
+        else {
+            (self.current_line, self.current_column) = self.lexer.tokens.end_position();
+            self.current_tok.clear();
+            String::new()
+        }
+    }
+
+    // Start parsing lolcode
+    fn parse(&mut self) {
+
+        //Call lolcode method to start parsing lolcode
+        self.lolcode();
+
+        //If input is left after the document, record an error
+        if self.lexer.tokens.peek().is_some() {
+            self.errors.push(CompileError::Structure {
+                line: self.token_line(),
+                message: "Additional tokens found after the document.".to_string(),
+            });
+        }
+    }
+
+    //Return the clone of current token
+    fn current_token(&self) -> String {
+        self.current_tok.clone()
+    }
+
+    //Set the current token of the compiler
+    fn set_current_token(&mut self, tok: String) {
+        self.current_tok = tok;
+    }
+}
+
+// Command line flags and what they do, printed by --help
+const FLAG_HELP: &[(&str, &str)] = &[
+    ("--tag-map <file>", "map elements to other output tags, one element=tag per line"),
+    ("--dir <dir>", "compile every .lol file found under a directory"),
+    ("--out-dir <dir>", "write the html files into a directory instead of the working directory"),
+    ("--shorthand", "accept the compact keyword set (#p ... #/p) alongside the regular keywords"),
+    ("--source-comments", "mark each emitted block with the source line it came from as <!-- src:N -->"),
+    ("--annotate", "mark each emitted block with the source line it came from as <!-- line N -->"),
+    ("--allowed-schemes <list>", "comma separated list of URL schemes accepted in addresses"),
+    ("--input-encoding <name>", "encoding of the source file, utf-8 or latin1"),
+    ("--comments <keep|strip>", "keep the comments as html comments or leave them out"),
+    ("--bundle <file.zip>", "write a zip with the html and the local media it references"),
+    ("--pedantic", "reject discouraged patterns that are normally tolerated"),
+    ("--strict", "report unknown # tags with the list of valid tags"),
+    ("--dump-tokens", "print the tokens of the lexer and stop"),
+    ("--stdout", "print the html instead of writing a file and opening it"),
+    ("--fragment", "emit only the body content, without the doctype, html and head"),
+    ("--verify", "check that every tag of the generated html is closed"),
+    ("--no-shadowing", "reject a variable declared with the name of a variable of an outer scope"),
+    ("--minify", "emit the html on a single line"),
+    ("--max-depth <n>", "report an error for elements nested deeper than n, 64 by default"),
+    ("--inline-media", "write the local audio and video files of up to 1 MB into the html as data URIs"),
+    ("--img-responsive", "let every image shrink to the width of the page"),
+    ("--default-title <text>", "title of a page without a title or headings"),
+    ("--json", "print the parsed document and the declared variables as JSON instead of writing the html"),
+    ("--verbose", "print the lines, tokens and variables processed once the html is written"),
+    ("--no-open", "only write the html file, do not open it in chrome"),
+    ("--output, -o <file.html>", "write the html to this path instead of the one derived from the file name"),
+    ("--help, -h", "print this help"),
+    ("--version", "print the version"),
+];
+
+// Usage, the flags and a summary of the tags of the language, the tags are taken from the default keywords
+fn help_text() -> String {
+    let keywords = KeywordConfig::default();
+    let names = |vectors: &[&Vec<String>]| -> String {
+        vectors.iter().filter_map(|vector| vector.first()).cloned().collect::<Vec<_>>().join(", ")
+    };
+    let maek = names(&[
+        &keywords.head_element, &keywords.paragraph_element, &keywords.list_element,
+        &keywords.head1_element, &keywords.head2_element, &keywords.head3_element,
+        &keywords.head4_element, &keywords.head5_element, &keywords.head6_element,
+        &keywords.table_element, &keywords.row_element, &keywords.quote_element,
+        &keywords.figure_element, &keywords.container_element, &keywords.body_element,
+    ]);
+    let gimmeh = names(&[
+        &keywords.title_element, &keywords.description_element, &keywords.stylesheet_element,
+        &keywords.bold_element, &keywords.italics_element, &keywords.strongem_element,
+        &keywords.item_element, &keywords.newline_element, &keywords.hrule_element,
+        &keywords.soundz_element, &keywords.vidz_element, &keywords.link_element,
+        &keywords.styled_element, &keywords.cell_element, &keywords.maths_element,
+        &keywords.code_element, &keywords.image_element,
+    ]);
+
+    let mut help = format!("Usage: {} <file.lol> [flags]\n\nFlags:\n", env!("CARGO_PKG_NAME"));
+    for (flag, description) in FLAG_HELP {
+        help.push_str(&format!("  {:<32}{}\n", flag, description));
+    }
+    help.push_str(&format!(
+        "\nTags: #hai ... #kthxbye, #obtw ... #tldr, #maek <{}> ... #oic, #gimmeh <{}> ... #mkay, #i haz name #it iz value #mkay, #lemme see name #mkay",
+        maek, gimmeh
+    ));
+    help
+}
+
+/**
+ * Action enum - what the command line asks for, built by Config::build and carried out by main
+ * 1. Compile - compile the file or directory of the config, boxed as it is much larger than the other variants
+ * 2. Help - print the usage, the flags and the tags
+ * 3. Version - print the name and version of the compiler
+ */
+enum Action {
+    Compile(Box<Config>),
+    Help,
+    Version,
+}
+
+//Custom class to validate a file path or report an error, includes a file path and the optional command line flags
+struct Config {
+    file_path: String,
+    tag_map_path: Option<String>,
+    dir_path: Option<String>,
+    out_dir: Option<String>,
+    shorthand: bool,
+    source_comments: bool,
+    allowed_schemes: Option<String>,
+    input_encoding: Option<String>,
+    strip_comments: bool,
+    bundle_path: Option<String>,
+    pedantic: bool,
+    open_browser: bool,
+    output_path: Option<String>,
+    strict: bool,
+    dump_tokens: bool,
+    stdout: bool,
+    fragment: bool,
+    verify: bool,
+    allow_shadowing: bool,
+    minify: bool,
+    json: bool,
+    verbose: bool,
+    max_depth: Option<usize>,
+    inline_media: bool,
+    annotate: bool,
+    img_responsive: bool,
+    default_title: Option<String>,
+}
+
+//implementation for Config
+impl Config {
+
+    //Report an error if no file path is found
+    fn build(args: &[String]) -> Result<Action, &'static str> {
+
+        //--help and --version do not need a file argument, main prints their text
+        if args.iter().skip(1).any(|arg| arg == "--help" || arg == "-h") {
+            return Ok(Action::Help);
+        }
+        if args.iter().skip(1).any(|arg| arg == "--version") {
+            return Ok(Action::Version);
+        }
+
+        if args.len() < 2 {
+            return Err("not enough arguments, add a file argument");
+        }
+
+        let mut file_path: Option<String> = None;
+        let mut tag_map_path: Option<String> = None;
+        let mut dir_path: Option<String> = None;
+        let mut out_dir: Option<String> = None;
+        let mut shorthand = false;
+        let mut source_comments = false;
+        let mut allowed_schemes: Option<String> = None;
+        let mut input_encoding: Option<String> = None;
+        let mut strip_comments = false;
+        let mut bundle_path: Option<String> = None;
+        let mut pedantic = false;
+        let mut open_browser = true;
+        let mut output_path: Option<String> = None;
+        let mut strict = false;
+        let mut dump_tokens = false;
+        let mut stdout = false;
+        let mut fragment = false;
+        let mut verify = false;
+        let mut allow_shadowing = true;
+        let mut minify = false;
+        let mut json = false;
+        let mut verbose = false;
+        let mut max_depth: Option<usize> = None;
+        let mut inline_media = false;
+        let mut annotate = false;
+        let mut img_responsive = false;
+        let mut default_title: Option<String> = None;
+
+        //Go through the arguments after the program name, flags take the argument that follows them
+        let mut remaining = args[1..].iter();
+        while let Some(arg) = remaining.next() {
+            if arg == "--tag-map" {
+                match remaining.next() {
+                    Some(path) => tag_map_path = Some(path.clone()),
+                    None => return Err("--tag-map requires a file argument"),
+                }
+            }
+
+            //Compile every .lol file found under a directory
+            else if arg == "--dir" {
+                match remaining.next() {
+                    Some(path) => dir_path = Some(path.clone()),
+                    None => return Err("--dir requires a directory argument"),
+                }
+            }
+
+            //Write the html files into a directory instead of the working directory
+            else if arg == "--out-dir" {
+                match remaining.next() {
+                    Some(path) => out_dir = Some(path.clone()),
+                    None => return Err("--out-dir requires a directory argument"),
+                }
+            }
+
+            //Accept the compact keyword set (#p ... #/p) alongside the regular keywords
+            else if arg == "--shorthand" {
+                shorthand = true;
+            }
+
+            //Mark each emitted block with the source line it came from
+            else if arg == "--source-comments" {
+                source_comments = true;
+            }
+
+            //Mark each emitted block with its source line for grading, written as <!-- line N -->
+            else if arg == "--annotate" {
+                annotate = true;
+            }
+
+            //Comma separated list of URL schemes accepted in addresses
+            else if arg == "--allowed-schemes" {
+                match remaining.next() {
+                    Some(schemes) => allowed_schemes = Some(schemes.clone()),
+                    None => return Err("--allowed-schemes requires a list like http,https"),
+                }
+            }
+
+            //Encoding of the source file, utf-8 or latin1
+            else if arg == "--input-encoding" {
+                match remaining.next() {
+                    Some(encoding) => input_encoding = Some(encoding.clone()),
+                    None => return Err("--input-encoding requires utf-8 or latin1"),
+                }
+            }
+
+            //Comment policy, keep emits the comments as html comments and strip leaves them out
+            else if arg == "--comments" {
+                match remaining.next().map(|policy| policy.as_str()) {
+                    Some("keep") => strip_comments = false,
+                    Some("strip") => strip_comments = true,
+                    _ => return Err("--comments requires keep or strip"),
+                }
+            }
+
+            //Write a zip with the html and the local media it references
+            else if arg == "--bundle" {
+                match remaining.next() {
+                    Some(path) => bundle_path = Some(path.clone()),
+                    None => return Err("--bundle requires a .zip file argument"),
+                }
+            }
+
+            //Reject discouraged patterns that are normally tolerated
+            else if arg == "--pedantic" {
+                pedantic = true;
+            }
+
+            //Report unknown # tags with the list of valid tags
+            else if arg == "--strict" {
+                strict = true;
+            }
+
+            //Print the tokens of the lexer and stop, used to debug the grammar
+            else if arg == "--dump-tokens" {
+                dump_tokens = true;
+            }
+
+            //Print the html instead of writing a file and opening it
+            else if arg == "--stdout" {
+                stdout = true;
+            }
+
+            //Emit only the body content, without the doctype, html and head
+            else if arg == "--fragment" {
+                fragment = true;
+            }
+
+            //Check that every tag of the generated html is closed, fail if one is not
+            else if arg == "--verify" {
+                verify = true;
+            }
+
+            //Reject a variable declared with the name of a variable of an outer scope
+            else if arg == "--no-shadowing" {
+                allow_shadowing = false;
+            }
+
+            //Emit the html on a single line
+            else if arg == "--minify" {
+                minify = true;
+            }
+
+            //Print the parsed document as JSON instead of writing the html
+            else if arg == "--json" {
+                json = true;
+            }
+
+            //Print the lines, tokens and variables processed once the html is written
+            else if arg == "--verbose" {
+                verbose = true;
+            }
+
+            //Only write the html file, do not open it in chrome
+            else if arg == "--no-open" {
+                open_browser = false;
+            }
+
+            //Deepest nesting of blocks and inline elements before an error is reported
+            else if arg == "--max-depth" {
+                match remaining.next().map(|depth| depth.parse::<usize>()) {
+                    Some(Ok(depth)) if depth > 0 => max_depth = Some(depth),
+                    _ => return Err("--max-depth requires a positive number"),
+                }
+            }
+
+            //Write the local audio and video into the html as data URIs
+            else if arg == "--inline-media" {
+                inline_media = true;
+            }
+
+            //Let the images shrink to the width of the page
+            else if arg == "--img-responsive" {
+                img_responsive = true;
+            }
+
+            //Title of a page that has no title and no headings
+            else if arg == "--default-title" {
+                match remaining.next() {
+                    Some(title) => default_title = Some(title.clone()),
+                    None => return Err("--default-title requires a title argument"),
+                }
+            }
+
+            //Write the html to this path instead of the one derived from the file name
+            else if arg == "--output" || arg == "-o" {
+                match remaining.next() {
+                    Some(path) if path.to_lowercase().ends_with(".html") => output_path = Some(path.clone()),
+                    Some(_) => return Err("--output requires a path ending in .html"),
+                    None => return Err("--output requires a .html file argument"),
+                }
+            }
+
+            //The first argument that is not a flag is the file path
+            else if file_path.is_none() {
+                file_path = Some(arg.clone());
+            }
+        }
+
+        //A directory is compiled file by file into html files, the flags that write or print something else do not apply to it
+        if dir_path.is_some() {
+            let single_file_flags = [
+                (file_path.is_some(), "a file argument cannot be used with --dir"),
+                (bundle_path.is_some(), "--bundle cannot be used with --dir"),
+                (json, "--json cannot be used with --dir"),
+                (stdout, "--stdout cannot be used with --dir"),
+                (dump_tokens, "--dump-tokens cannot be used with --dir"),
+                (output_path.is_some(), "--output cannot be used with --dir, use --out-dir"),
+            ];
+            if let Some((_used, message)) = single_file_flags.iter().find(|(used, _message)| *used) {
+                return Err(message);
+            }
+        }
+
+        //A file path is only optional when compiling a directory
+        let file_path = match file_path {
+            Some(file_path) => file_path,
+            None if dir_path.is_some() => String::new(),
+            None => return Err("not enough arguments, add a file argument"),
+        };
+
+        //file_path validated, returns OK
+        Ok(Action::Compile(Box::new(Config {
+            file_path,
+            tag_map_path,
+            dir_path,
+            out_dir,
+            shorthand,
+            source_comments,
+            allowed_schemes,
+            input_encoding,
+            strip_comments,
+            bundle_path,
+            pedantic,
+            open_browser,
+            output_path,
+            strict,
+            dump_tokens,
+            stdout,
+            fragment,
+            verify,
+            allow_shadowing,
+            minify,
+            json,
+            verbose,
+            max_depth,
+            inline_media,
+            annotate,
+            img_responsive,
+            default_title,
+        })))
+    }
+}
+
+
+//Format tokens one per line as line:column and the token, in document order
+fn format_tokens(tokens: &[(String, usize, usize)]) -> String {
+    tokens
+        .iter()
+        .map(|(token, line, column)| format!("{}:{}\t{}\n", line, column, token))
+        .collect()
+}
+
+
+// Tags that have no closing tag in html, they are not expected to be closed by --verify
+const VOID_TAGS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+//Scan the generated html and describe every tag that is not closed, or closed without being opened, used by --verify
+//Comments and the doctype are skipped, text never contains '<' because it is escaped
+fn unbalanced_tags(html: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut rest = html;
+    let mut line = 1;
+
+    while let Some(start) = rest.find('<') {
+        line += rest[..start].matches('\n').count();
+        rest = &rest[start..];
+
+        //Skip comments and declarations like <!DOCTYPE html>
+        let end_marker = if rest.starts_with("<!--") { "-->" } else { ">" };
+        let Some(end) = rest.find(end_marker) else {
+            problems.push(format!("line {}: tag is not closed with '{}'.", line, end_marker));
+            break;
+        };
+        let tag = &rest[1..end];
+        line += tag.matches('\n').count();
+        rest = &rest[end + end_marker.len()..];
+        if tag.starts_with('!') {
+            continue;
+        }
+
+        //Name of the tag, without the attributes
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '/')
+            .collect::<String>()
+            .to_lowercase();
+
+        if closing {
+            match open.pop() {
+                Some((opened, _opened_line)) if opened == name => {}
+                Some((opened, opened_line)) => {
+                    problems.push(format!("line {}: </{}> closes <{}> opened at line {}.", line, name, opened, opened_line));
+                }
+                None => problems.push(format!("line {}: </{}> has no opening tag.", line, name)),
+            }
+        } else if !tag.ends_with('/') && !VOID_TAGS.contains(&name.as_str()) {
+            open.push((name, line));
+        }
+    }
+
+    //Tags left open at the end of the document
+    for (name, opened_line) in open.iter().rev() {
+        problems.push(format!("line {}: <{}> is never closed.", opened_line, name));
+    }
+
+    problems
+}
+
+//Recursively collect all .lol files under a directory, sorted so the batch order is stable
+fn find_lol_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        //Go into sub directories
+        if path.is_dir() {
+            find_lol_files(&path, files)?;
+        }
+
+        //Keep files with .lol extension
+        else if path.extension().and_then(|ext| ext.to_str()) == Some("lol") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+//Compile every .lol file under a directory and print a summary, a failing file does not stop the batch
+//Every file is compiled in this process by a compiler of its own set up from the config, so the time is the time spent compiling
+fn run_batch(config: &Config) {
+    let dir = Path::new(config.dir_path.as_deref().unwrap_or("."));
+
+    //Collect the files to compile, report an error if the directory cannot be read
+    let mut files = Vec::new();
+    if let Err(e) = find_lol_files(dir, &mut files) {
+        println!("Error reading the directory {}: {e}", dir.display());
+        process::exit(1);
+    }
+
+    //Create the output directory once, the html files are written next to their source files without one
+    if let Some(out_dir) = &config.out_dir {
+        if let Err(e) = fs::create_dir_all(out_dir) {
+            println!("Error creating the output directory {out_dir}: {e}");
+            process::exit(1);
+        }
+    }
+
+    let start = Instant::now();
+    let mut successes = 0;
+    let mut failures = 0;
+
+    for file in &files {
+        let out_dir = match &config.out_dir {
+            Some(out_dir) => Path::new(out_dir),
+            None => file.parent().unwrap_or(Path::new(".")),
+        };
+
+        //Count the result, report the errors of the file that failed
+        match compile_batch_file(config, file, out_dir) {
+            Ok(()) => successes += 1,
+            Err(messages) => {
+                for message in &messages {
+                    eprintln!("{}", message);
+                }
+                println!("Failed: {}", file.display());
+                failures += 1;
+            }
+        }
+    }
+
+    //Print the summary of the batch
+    println!(
+        "Compiled {} files: {} succeeded, {} failed in {:.2?}",
+        files.len(),
+        successes,
+        failures,
+        start.elapsed()
+    );
+
+    if failures > 0 {
+        process::exit(1);
+    }
+}
+
+//Compile one file of a batch into its html file in the output directory, the messages of the errors are returned if it fails
+//The warnings are printed with the file they belong to, chrome is never opened for a batch
+fn compile_batch_file(config: &Config, file: &Path, out_dir: &Path) -> Result<(), Vec<String>> {
+    let source = read_source(&file.to_string_lossy(), config.input_encoding.as_deref())
+        .map_err(|e| vec![format!("Error reading the file: {e}")])?;
+
+    let mut compiler = configured_compiler(config);
+    let result = compile_with_media(&mut compiler, config, &source, file.parent().unwrap_or(Path::new(".")));
+    for warning in &compiler.warnings {
+        eprintln!("Warning: {}: {}", file.display(), warning);
+    }
+    let html = result.map_err(|errors| errors.iter().map(|error| error.to_string()).collect::<Vec<_>>())?;
+
+    //Report the tags that are not balanced if requested
+    if config.verify {
+        let problems = unbalanced_tags(&html);
+        if !problems.is_empty() {
+            return Err(problems.iter().map(|problem| format!("Verify error at {}", problem)).collect());
+        }
+    }
+
+    let html_path = out_dir.join(html_file_name(file));
+    let written_path = write_html(&html, &html_path).map_err(|e| vec![format!("Error writing the file {}: {e}", html_path.display())])?;
+
+    if config.verbose {
+        let summary = compile_summary(source.lines().count(), compiler.language_tokens.len(), compiler.declarations.len(), &written_path.display().to_string());
+        println!("{}", summary);
+    }
+
+    Ok(())
+}
+
+//Name of the html file of a .lol file, the file name with the .html extension
+fn html_file_name(file_path: &Path) -> String {
+    file_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .map(|name| format!("{}.html", name))
+        .unwrap_or_else(|| "output.html".to_string())
+}
+
+//Set up a compiler with the options of the command line, used for the file of a single run and for every file of a batch
+fn configured_compiler(config: &Config) -> LolcodeCompiler {
+    let mut compiler = LolcodeCompiler::new();
+
+    //Override the element tags if a tag map is given
+    if let Some(tag_map_path) = &config.tag_map_path {
+        compiler.tag_map = TagMap::load(tag_map_path);
+    }
+
+    //Use the compact keyword set if requested
+    compiler.shorthand = config.shorthand;
+
+    //Explain unknown tags if requested
+    compiler.strict = config.strict;
+
+    //Mark the emitted blocks with their source lines if requested
+    compiler.source_comments = config.source_comments;
+    compiler.annotate = config.annotate;
+
+    //Let the images shrink to the width of the page if requested
+    compiler.img_responsive = config.img_responsive;
+
+    //Title of a page without a title or headings
+    if let Some(default_title) = &config.default_title {
+        compiler.default_title = default_title.clone();
+    }
+
+    //Apply the comment policy
+    compiler.strip_comments = config.strip_comments;
+
+    //Leave out the doctype, html and head if only the body content is requested
+    compiler.fragment = config.fragment;
+
+    //Reject shadowed variables if requested
+    compiler.allow_shadowing = config.allow_shadowing;
+
+    //Emit the html on a single line if requested
+    compiler.minify = config.minify;
+
+    //Replace the maximum nesting depth if one is given
+    if let Some(max_depth) = config.max_depth {
+        compiler.max_depth = max_depth;
+    }
+
+    //Replace the allowed URL schemes if a list is given
+    if let Some(allowed_schemes) = &config.allowed_schemes {
+        compiler.allowed_schemes = allowed_schemes
+            .split(',')
+            .map(|scheme| scheme.trim().trim_end_matches(':').to_lowercase())
+            .filter(|scheme| !scheme.is_empty())
+            .collect();
+    }
+
+    //In pedantic mode, discouraged patterns are errors too
+    compiler.pedantic = config.pedantic;
+
+    compiler
+}
+
+//Compile a program string into html, with the local audio and video inlined as data URIs if requested
+//The media addresses are resolved against the directory of the .lol file
+fn compile_with_media(compiler: &mut LolcodeCompiler, config: &Config, source: &str, source_dir: &Path) -> Result<String, Vec<CompileError>> {
+    let html = compiler.compile_to_html(source)?;
+    if !config.inline_media {
+        return Ok(html);
+    }
+
+    //Convert again so the media addresses are the data URIs
+    compiler.inline_media(source_dir);
+    compiler.render_output().map_err(|error| vec![error])
+}
+
+
+//Read the source file as bytes and decode it, utf-8 by default or latin1 when --input-encoding latin1 is given
+fn read_source(file_path: &str, encoding: Option<&str>) -> Result<String, String> {
+    let bytes = fs::read(file_path).map_err(|e| read_error_message(&e, Path::new(file_path)))?;
+
+    match encoding.map(|encoding| encoding.to_lowercase()).as_deref() {
+        None | Some("utf-8") | Some("utf8") => {
+            //Report the byte offset of the first invalid sequence so the user can find it
+            String::from_utf8(bytes).map_err(|e| {
+                format!(
+                    "invalid UTF-8 at byte {}. If the file is Latin-1 encoded, try --input-encoding latin1",
+                    e.utf8_error().valid_up_to()
+                )
+            })
+        }
+
+        //Every latin1 byte is the unicode character with the same value
+        Some("latin1") | Some("latin-1") | Some("iso-8859-1") => {
+            Ok(bytes.iter().map(|&byte| byte as char).collect())
+        }
+
+        Some(other) => Err(format!("unsupported input encoding '{}', use utf-8 or latin1", other)),
+    }
+}
+
+//Explain why the source file could not be read, the common cases get a message of their own with the absolute path
+fn read_error_message(error: &io::Error, path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match error.kind() {
+        io::ErrorKind::NotFound => format!("file not found: {}", path.display()),
+        io::ErrorKind::PermissionDenied => format!("permission denied, the file cannot be read: {}", path.display()),
+        io::ErrorKind::IsADirectory => format!("{} is a directory, use --dir to compile the .lol files inside it", path.display()),
+        _ => format!("{}: {}", path.display(), error),
+    }
+}
+//Summary of a successful compile printed by --verbose
+fn compile_summary(lines: usize, tokens: usize, variables: usize, output: &str) -> String {
+    format!(
+        "Processed {} lines, {} tokens and {} declared variables, output: {}",
+        lines, tokens, variables, output
+    )
+}
+
+//Write the html to a file and return the absolute path it was written to
+fn write_html(html: &str, path: &Path) -> io::Result<PathBuf> {
+    fs::write(path, html)?;
+    std::path::absolute(path)
+}
+
+//Write the html and the bundled assets into a zip file, assets are stored under the name the html refers to them by
+fn write_bundle(bundle_path: &str, html_name: &str, html: &str, assets: &[(PathBuf, String)]) -> io::Result<()> {
+    let file = File::create(bundle_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default();
+
+    //Add the html page
+    zip.start_file(html_name, options).map_err(io::Error::other)?;
+    zip.write_all(html.as_bytes())?;
+
+    //Copy every asset into the zip
+    for (source_path, bundle_name) in assets {
+        let contents = fs::read(source_path)?;
+        zip.start_file(bundle_name.as_str(), options).map_err(io::Error::other)?;
+        zip.write_all(&contents)?;
+    }
+
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+
+//Function to open chrome in html
+pub fn open_html_in_chrome<P: AsRef<Path>>(html_file: P) -> io::Result<()> {
+
+    //Get the reference of file
+    let p = html_file.as_ref();
+    
+
+    //If path does not exist,  report error
+    if !p.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File not found: {}", p.display()),
+        ));
+    }
+    
+    //Cannonicalize path to URL 
+    let abs = fs::canonicalize(p)?;
+    let file_url = file_url(&abs)?;
+
+    //Open the URL with the browser of the platform
+    launch_browser(&file_url)
+}
+
+
+//Message printed when the browser could not be opened, points to the html file so it can be opened manually
+fn browser_error_message(html_file: &Path, error: &io::Error) -> String {
+    format!(
+        "Error opening the browser: {}. The html file was written to {}, open it manually.",
+        error,
+        html_file.display()
+    )
+}
+
+
+//Convert an absolute path to a file:// URL, works for windows (C:\dir) and unix (/dir) paths
+fn file_url(abs: &Path) -> io::Result<String> {
+
+    // Handle potential non-UTF8 paths gracefully
+    let path_str = abs.to_str().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Path contains invalid UTF-8")
+    })?;
+    
+
+    //Get the clean path, windows paths may have the \\?\ prefix and unix paths already start with /
+    let clean_path = path_str.strip_prefix(r"\\?\").unwrap_or(path_str);
+    let clean_path = clean_path.replace('\\', "/");
+    
+    // Convert to file:// URL for chrome display
+    Ok(format!("file:///{}", clean_path.trim_start_matches('/')))
+}
+
+
+//Open a URL in chrome on windows
+#[cfg(windows)]
+fn launch_browser(file_url: &str) -> io::Result<()> {
+
+    // Try to find Chrome from registry if not defined in path
+    if let Some(chrome_path) = find_chrome_path() {
+        return Command::new(chrome_path)
+            .arg(file_url)
+            .spawn()
+            .map(|_| ())
+            .map_err(io::Error::other);
+    }
+    
+    // Fallback to 'start chrome' command
+    let status = Command::new("cmd")
+        .args(["/C", "start", "chrome", file_url])
+        .status()?;
+
+    
+    //Return success or error if chrome is not launced
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other("Could not find or launch Chrome"))
+    }
+}
+
+
+//Open a URL in chrome on macOS, fall back to the default browser if chrome is not installed
+#[cfg(target_os = "macos")]
+fn launch_browser(file_url: &str) -> io::Result<()> {
+    let chrome = Command::new("open").args(["-a", "Google Chrome", file_url]).status()?;
+    if chrome.success() {
+        return Ok(());
+    }
+
+    let status = Command::new("open").arg(file_url).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other("Could not find or launch a browser"))
+    }
+}
+
+
+//Open a URL in chrome on linux, fall back to the default browser through xdg-open
+#[cfg(target_os = "linux")]
+fn launch_browser(file_url: &str) -> io::Result<()> {
+    for chrome in ["google-chrome", "google-chrome-stable", "chromium", "chromium-browser"] {
+        if Command::new(chrome).arg(file_url).spawn().is_ok() {
+            return Ok(());
+        }
+    }
+
+    let status = Command::new("xdg-open").arg(file_url).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other("Could not find or launch a browser"))
+    }
+}
+
+
+//No browser launching on other platforms, the html file is still written
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn launch_browser(_file_url: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Opening a browser is not supported on this platform"))
+}
+
+
+//Find chrome path in system 
+#[cfg(windows)]
+fn find_chrome_path() -> Option<String> {
+    // Registry keys where Chrome might be registered
+    let registry_paths = [
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths\chrome.exe",
+        r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\App Paths\chrome.exe",
+        r"SOFTWARE\Google\Chrome\BLBeacon",
+    ];
+    
+    //predefinition of how registry will look like
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+    
+    // Try each registry path, and associated keys, till a path where chrome exists is found
+    for reg_path in &registry_paths {
+        if let Ok(key) = hklm.open_subkey(reg_path) {
+            // Try to read the default value or "Path" value
+            if let Ok(path) = key.get_value::<String, _>("") {
+                if Path::new(&path).exists() {
+                    return Some(path);
+                }
+            }
+            
+            // Some keys store it in a "version" subkey
+            if let Ok(path) = key.get_value::<String, _>("Path") {
+                let chrome_exe = format!("{}\\chrome.exe", path);
+                if Path::new(&chrome_exe).exists() {
+                    return Some(chrome_exe);
+                }
+            }
+        }
+    }
+    
+    // Also try HKEY_CURRENT_USER by parsing throuhg all the paths
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    for reg_path in &registry_paths {
+        if let Ok(key) = hkcu.open_subkey(reg_path) {
+            if let Ok(path) = key.get_value::<String, _>("") {
+                if Path::new(&path).exists() {
+                    return Some(path);
+                }
+            }
+        }
+    }
+    
+    // Chrome may be installed without being registered, check the default install locations
+    first_existing_path(&default_chrome_paths())
+}
+
+//Default install locations of chrome, for all users in both program files folders and for the current user
+#[cfg(windows)]
+fn default_chrome_paths() -> Vec<PathBuf> {
+    let chrome_exe = r"Google\Chrome\Application\chrome.exe";
+    let folders = [
+        ("ProgramFiles", r"C:\Program Files"),
+        ("ProgramFiles(x86)", r"C:\Program Files (x86)"),
+        ("LOCALAPPDATA", ""),
+    ];
+
+    folders
+        .iter()
+        .filter_map(|(variable, default)| match env::var(variable) {
+            Ok(folder) => Some(folder),
+            Err(_) if !default.is_empty() => Some(default.to_string()),
+            Err(_) => None,
+        })
+        .map(|folder| Path::new(&folder).join(chrome_exe))
+        .collect()
+}
+
+//Return the first candidate path that exists as a file
+#[cfg(windows)]
+fn first_existing_path(candidates: &[PathBuf]) -> Option<String> {
+    candidates
+        .iter()
+        .find(|candidate| candidate.is_file())
+        .map(|candidate| candidate.display().to_string())
+}
+
+
+fn main() {
+
+    //Collect all file arguments
+    let args: Vec<String> = env::args().collect();
+
+    //Report error if problem parsing arguments, --help and --version print their text and exit
+    let config = match Config::build(&args) {
+        Ok(Action::Compile(config)) => *config,
+        Ok(Action::Help) => {
+            println!("{}", help_text());
+            return;
+        }
+        Ok(Action::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(err) => {
+            println!("Problem parsing arguments: {err}");
+            process::exit(1);
+        }
+    };
+
+    //Compile a whole directory if --dir is given
+    if config.dir_path.is_some() {
+        run_batch(&config);
+        return;
+    }
+
+// Validate .lol extension
+let file_path = Path::new(&config.file_path);
+match file_path.extension().and_then(|ext| ext.to_str()) {
+    Some("lol") => {
+        //Continue
+    }
+    //Error - wrong file extension
+    Some(other) => {
+        println!("Error: Invalid file extension '.{}'. Only .lol files are accepted.", other);
+        process::exit(1);
+    }
+    //Extension not found
+    None => {
+        println!("Error: No file extension found. Only .lol files are accepted.");
+        process::exit(1);
+    }
+}
+
+//Initialize html file at file path based on first name of .lol file in the same location
+let html_filename = html_file_name(file_path);
+
+//Use the output path if one is given, else place the html file inside the output directory if one is given, create it if it does not exist
+let html_filename = match (&config.output_path, &config.out_dir) {
+    (Some(output_path), _) => PathBuf::from(output_path),
+    (None, Some(out_dir)) => {
+        if let Err(e) = fs::create_dir_all(out_dir) {
+            println!("Error creating the output directory {out_dir}: {e}");
+            process::exit(1);
+        }
+        Path::new(out_dir).join(html_filename)
+    }
+    (None, None) => PathBuf::from(html_filename),
+};
+
+//Read string from file and set into lolcode string
+    let lolcode_string: String = match read_source(&config.file_path, config.input_encoding.as_deref()) {
+        Ok(contents) => contents,
+
+        //Report an error if not able to read or decode the file
+        Err(e) => {
+            println!("Error reading the file: {e}");
+            process::exit(1);
+        }
+    };
+
+    //Initialize a compiler with the options of the command line
+    let mut compiler = configured_compiler(&config);
+
+    //Print the token stream without parsing if requested
+    if config.dump_tokens {
+        compiler.compile(&lolcode_string);
+        print!("{}", format_tokens(&compiler.language_tokens));
+        return;
+    }
+
+    //Compile the file into html, with the local audio and video inlined as data URIs if requested
+    let source_dir = file_path.parent().unwrap_or(Path::new("."));
+    let result = compile_with_media(&mut compiler, &config, &lolcode_string, source_dir);
+
+    //Print the warnings, they do not stop the compiler
+    for warning in &compiler.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    //Report every error found and stop
+    let html_string: String = match result {
+        Ok(html) => html,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            process::exit(1);
+        }
+    };
+
+    //Print the parsed document as JSON if requested, nothing is written or opened
+    if config.json {
+        print!("{}", compiler.to_json());
+        return;
+    }
+
+    //Report the tags that are not balanced and stop if requested
+    if config.verify {
+        let problems = unbalanced_tags(&html_string);
+        if !problems.is_empty() {
+            for problem in &problems {
+                eprintln!("Verify error at {}", problem);
+            }
+            process::exit(1);
+        }
+    }
+
+    //Write a zip bundle instead of a html file if requested
+    if let Some(bundle_path) = &config.bundle_path {
+
+        //Local assets are resolved next to the .lol file
+        let mut assets: Vec<(PathBuf, String)> = Vec::new();
+
+        for (address, _kind) in compiler.local_assets() {
+            let asset_path = source_dir.join(&address);
+
+            //Missing assets are left as they are
+            if !asset_path.is_file() {
+                eprintln!("Warning: asset '{}' not found at {}, it is not bundled.", address, asset_path.display());
+                continue;
+            }
+
+            //Store assets by file name, numbered if two assets share a name
+            let file_name = asset_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("asset")
+                .to_string();
+            let mut bundle_name = format!("assets/{}", file_name);
+            let mut counter = 1;
+            while assets.iter().any(|(_path, name)| name == &bundle_name) {
+                bundle_name = format!("assets/{}-{}", counter, file_name);
+                counter += 1;
+            }
+
+            compiler.asset_urls.insert(address, bundle_name.clone());
+            assets.push((asset_path, bundle_name));
+        }
+
+        //Convert again so the asset addresses point inside the bundle
+        let html_string: String = compiler.render_output().unwrap_or_else(|error| report(error));
+
+        //The html keeps its name inside the bundle
+        let html_name = html_filename
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("output.html")
+            .to_string();
+
+        if let Err(e) = write_bundle(bundle_path, &html_name, &html_string, &assets) {
+            println!("Error writing the bundle {bundle_path}: {e}");
+            process::exit(1);
+        }
+
+        println!("Wrote bundle {} with {} assets", bundle_path, assets.len());
+        return;
+    }
+
+
+    //Counts of what was compiled, printed by --verbose
+    let lines = lolcode_string.lines().count();
+    let tokens = compiler.language_tokens.len();
+    let variables = compiler.declarations.len();
+
+    //Print the whole document if requested, nothing is written or opened, the summary goes to stderr to keep the html clean
+    if config.stdout {
+        print!("{}", html_string);
+        if config.verbose {
+            eprintln!("{}", compile_summary(lines, tokens, variables, "stdout"));
+        }
+        return;
+    }
+
+    //Write the html to the file, report an error if it cannot be written
+    let written_path = match write_html(&html_string, &html_filename) {
+        Ok(path) => path,
+        Err(e) => {
+            println!("Error writing the file {}: {e}", html_filename.display());
+            process::exit(1);
+        }
+    };
+
+    //Tell the user where the html file is
+    println!("Wrote {}", written_path.display());
+    if config.verbose {
+        println!("{}", compile_summary(lines, tokens, variables, &written_path.display().to_string()));
+    }
+
+    //open the file in html, unless --no-open is given
+    if config.open_browser {
+        //Tell the user where the html file is if the browser could not be opened
+        if let Err(e) = open_html_in_chrome(&written_path) {
+            println!("{}", browser_error_message(&written_path, &e));
+            process::exit(1);
+        }
+    }
+    
+   
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A document with a head, the body is put between the head and #kthxbye
+    fn page(body: &str) -> String {
+        format!("#hai #maek head #gimmeh title t #mkay #oic {} #kthxbye", body)
+    }
+
+    // The keywords of a dialect that renames the variable tags
+    fn variable_dialect() -> KeywordConfig {
+        KeywordConfig {
+            variable_start: vec!["#we".into(), "got".into()],
+            variable_mid: vec!["#that".into(), "be".into()],
+            variable_end: vec!["#show".into(), "me".into()],
+            ..KeywordConfig::default()
+        }
+    }
+
+    #[test]
+    fn variables_use_the_words_of_the_dialect() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.keywords = variable_dialect();
+        let html = compiler
+            .compile_to_html(&page("#we got name #that be world #mkay #maek paragraf hello #show me name #mkay #oic"))
+            .unwrap();
+        assert!(html.contains("<p>hello world</p>"), "{}", html);
+    }
+
+    #[test]
+    fn variable_errors_name_the_words_of_the_dialect() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.keywords = variable_dialect();
+        let errors = compiler.compile_to_html(&page("#we name #that be world #mkay")).unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Syntax { expected, found, .. } if expected == &["got"] && found == "name"), "{:?}", errors);
+    }
+
+    #[test]
+    fn variable_define_reports_the_missing_half() {
+        let error = compile_str(&page("#i name #it iz x #mkay")).unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { expected, .. } if expected == &["haz"]), "{:?}", error);
+    }
+
+    // A compiler that has parsed the document, for the tests that look at the parsed tree
+    fn checked(source: &str) -> LolcodeCompiler {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.check(source).unwrap();
+        compiler
+    }
+
+    #[test]
+    fn local_assets_come_from_the_media_elements_only() {
+        let compiler = checked(&page(
+            "#maek paragraf I like vidz a lot and soundz too #oic #gimmeh soundz song.mp3 http://a.com/b.mp3 #mkay #gimmeh vidz clip.mp4 #mkay",
+        ));
+        assert_eq!(
+            compiler.local_assets(),
+            vec![("song.mp3".to_string(), AssetKind::Audio), ("clip.mp4".to_string(), AssetKind::Video)]
+        );
+    }
+
+    #[test]
+    fn showcase_wraps_the_image_and_its_escaped_caption() {
+        let html = compile_str(&page("#maek showcase #gimmeh piccy cat.png a cat #mkay Tom & \"Jerry\" #oic")).unwrap();
+        assert!(
+            html.contains("<figure>\n      <img src=\"cat.png\" alt=\"a cat\"/>\n      <figcaption>Tom &amp; &quot;Jerry&quot;</figcaption>\n    </figure>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn local_assets_include_the_image_of_a_showcase_but_not_a_piccy_in_text() {
+        let compiler = checked(&page("#maek paragraf a piccy here #oic #maek showcase #gimmeh piccy cat.png a cat #mkay caption #oic"));
+        assert_eq!(compiler.local_assets(), vec![("cat.png".to_string(), AssetKind::Image)]);
+    }
+
+    #[test]
+    fn inlined_media_takes_its_mime_type_from_the_element() {
+        let compiler = checked(&page("#gimmeh soundz song.ogg #mkay #gimmeh vidz clip.ogg #mkay #gimmeh piccy cat.png #mkay"));
+        let mime_types: Vec<Option<&str>> = compiler.local_assets().iter().map(|(address, kind)| media_mime_type(address, *kind)).collect();
+        assert_eq!(mime_types, vec![Some("audio/ogg"), Some("video/ogg"), None]);
+    }
+
+    #[test]
+    fn inline_media_reads_the_files_next_to_the_source() {
+        let folder = env::temp_dir().join(format!("lolcode_inline_media_{}", process::id()));
+        fs::create_dir_all(folder.join("media")).unwrap();
+        let source = folder.join("page.lol");
+        fs::write(&source, "").unwrap();
+        fs::write(folder.join("media/song.mp3"), b"lol").unwrap();
+        fs::write(folder.join("clip.ogg"), b"lol").unwrap();
+        fs::write(folder.join("big.mp4"), vec![0u8; INLINE_MEDIA_LIMIT as usize + 1]).unwrap();
+
+        let mut compiler = checked(&page(
+            "#gimmeh soundz media/song.mp3 missing.wav #mkay #gimmeh vidz clip.ogg #mkay #gimmeh vidz big.mp4 #mkay #gimmeh piccy cat.png #mkay",
+        ));
+        compiler.inline_media(source.parent().unwrap());
+        let html = compiler.render_output().unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert!(html.contains("<source src=\"data:audio/mpeg;base64,bG9s\" type=\"audio/mpeg\">"), "{}", html);
+        assert!(html.contains("<iframe src=\"data:video/ogg;base64,bG9s\"></iframe>"), "{}", html);
+
+        //A missing or large file and an image keep their addresses
+        assert!(html.contains("<source src=\"missing.wav\" type=\"audio/wav\">"), "{}", html);
+        assert!(html.contains("<iframe src=\"big.mp4\"></iframe>") && html.contains("<img src=\"cat.png\""), "{}", html);
+        assert_eq!(compiler.warnings.len(), 2, "{:?}", compiler.warnings);
+        assert!(compiler.warnings[0].starts_with("media 'missing.wav' is not inlined, file not found: "), "{:?}", compiler.warnings);
+        assert_eq!(
+            compiler.warnings[1],
+            format!("media 'big.mp4' is not inlined, it is {} bytes and only files up to {} bytes are inlined.", INLINE_MEDIA_LIMIT + 1, INLINE_MEDIA_LIMIT)
+        );
+    }
+
+    // A document that compiles normally but breaks every --pedantic rule, one kind of problem on each line
+    const PEDANTIC_DOCUMENT: &str = "#hai #maek head #gimmeh title t #mkay #oic
+#i haz x #it iz 1 #mkay
+#MAEK paragraf a #gimmeh newline #gimmeh newline b #OIC
+#maek paragraf #oic
+#gimmeh bold #mkay
+#kthxbye";
+
+    #[test]
+    fn pedantic_reports_every_discouraged_pattern() {
+        assert!(LolcodeCompiler::new().check(PEDANTIC_DOCUMENT).is_ok());
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.pedantic = true;
+        let errors: Vec<String> = compiler.check(PEDANTIC_DOCUMENT).unwrap_err().iter().map(|error| error.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "Pedantic error at line 3: tag '#MAEK' should be written in lowercase as '#maek'.",
+                "Pedantic error at line 3: tag '#OIC' should be written in lowercase as '#oic'.",
+                "Pedantic error at line 3: multiple consecutive newlines.",
+                "Pedantic error at line 4: empty 'paragraf' block.",
+                "Pedantic error at line 5: empty 'bold' element.",
+                "Pedantic error at line 2: variable 'x' is declared but never used.",
+            ]
+        );
+    }
+
+    #[test]
+    fn pedantic_ignores_keywords_in_text() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.pedantic = true;
+        let source = page("#i haz x #it iz 1 #mkay #maek paragraf come and see what iz there, haz a newline #lemme see x #mkay #oic");
+        let result = compiler.check(&source);
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn boxz_takes_a_dotted_class() {
+        let html = compile_str(&page("#maek boxz .intro Hello world #oic")).unwrap();
+        assert!(html.contains("<div class=\"intro\">Hello world</div>"), "{}", html);
+    }
+
+    #[test]
+    fn boxz_without_a_class_keeps_its_first_word() {
+        let html = compile_str(&page("#maek boxz Hello world #oic")).unwrap();
+        assert!(html.contains("<div>Hello world</div>"), "{}", html);
+    }
+
+    // Compile the source with --minify
+    fn minified(source: &str) -> String {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.minify = true;
+        compiler.compile_to_html(source).unwrap()
+    }
+
+    #[test]
+    fn minify_joins_the_tags_and_keeps_the_text() {
+        let html = minified(&page("#maek paragraf Hello World #oic #maek list #gimmeh item a #mkay #oic"));
+        assert!(!html.contains('\n'), "{}", html);
+        assert!(html.contains("<body><p>Hello World</p><ul><li>a</li></ul></body>"), "{}", html);
+    }
+
+    #[test]
+    fn minify_keeps_the_whitespace_of_code() {
+        let html = minified(&page("#maek paragraf code #gimmeh codez let  x =\n    1\n  done #mkay ok #oic"));
+        assert!(html.contains("<p>code <code>let  x =\n    1\n  done</code> ok</p>"), "{}", html);
+    }
+
+    #[test]
+    fn codez_escapes_its_text_and_keeps_its_spacing() {
+        let source = page("#maek paragraf #gimmeh codez a  <tag>\n  b #mkay #oic");
+        let html = compile_str(&source).unwrap();
+        assert!(html.contains("<code>a  &lt;tag&gt;\n  b</code>"), "{}", html);
+
+        //Minifying only removes the indentation around the code, the code itself is the same
+        let minified = minified(&source);
+        assert!(minified.contains("<p><code>a  &lt;tag&gt;\n  b</code></p>"), "{}", minified);
+    }
+
+    #[test]
+    fn annotate_marks_each_block_with_its_line() {
+        let source = "#hai #maek head #gimmeh title t #mkay #oic\n#maek paragraf one #oic\n\n#maek paragraf two #oic #kthxbye";
+        let mut compiler = LolcodeCompiler::new();
+        compiler.annotate = true;
+        let html = compiler.compile_to_html(source).unwrap();
+        assert!(html.contains("<!-- line 2 -->\n    <p>one</p>\n    <!-- line 4 -->\n    <p>two</p>"), "{}", html);
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.source_comments = true;
+        let html = compiler.compile_to_html(source).unwrap();
+        assert!(html.contains("<!-- src:2 -->\n    <p>one</p>"), "{}", html);
+    }
+
+    // The command line arguments with the program name first
+    fn args(arguments: &[&str]) -> Vec<String> {
+        std::iter::once("compiler_project").chain(arguments.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn help_and_version_need_no_file() {
+        assert!(matches!(Config::build(&args(&["--help"])), Ok(Action::Help)));
+        assert!(matches!(Config::build(&args(&["missing.lol", "-h"])), Ok(Action::Help)));
+        assert!(matches!(Config::build(&args(&["--version"])), Ok(Action::Version)));
+        assert!(matches!(Config::build(&args(&["page.lol"])), Ok(Action::Compile(config)) if config.file_path == "page.lol"));
+        assert!(Config::build(&args(&[])).is_err());
+    }
+
+    #[test]
+    fn undeclared_variable_is_unresolved() {
+        let error = compile_str(&page("#maek paragraf #lemme see foo #mkay #oic")).unwrap_err();
+        assert!(matches!(&error, CompileError::UnresolvedVariable { line: 1, name } if name == "foo"), "{:?}", error);
+    }
+
+    #[test]
+    fn to_html_reports_a_variable_without_a_value() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.document = Some(HtmlNode::Document(vec![HtmlNode::Paragraph {
+            line: 3,
+            children: vec![HtmlNode::VarUse { name: "foo".to_string(), value: None, line: 3 }],
+        }]));
+        assert!(matches!(compiler.to_html(), Err(CompileError::UnresolvedVariable { line: 3, name }) if name == "foo"));
+    }
+
+    #[test]
+    fn variable_without_a_value_emits_nothing() {
+        let html = compile_str(&page("#i haz x #mkay #maek paragraf a #lemme see x #mkay b #oic")).unwrap();
+        assert!(html.contains("<p>a b</p>"), "{}", html);
+    }
+
+    #[test]
+    fn unterminated_paragraph_is_the_only_error() {
+        let errors = LolcodeCompiler::new().check("#hai #maek head #gimmeh title t #mkay #oic\n#maek paragraf hello\nworld").unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(
+            errors[0].to_string(),
+            "Syntax error: Unterminated paragraf starting at line 2, the input ended before it was closed."
+        );
+    }
+
+    #[test]
+    fn missing_kthxbye_is_still_reported() {
+        let errors = LolcodeCompiler::new().check("#hai #maek head #gimmeh title t #mkay #oic #maek paragraf hello #oic").unwrap_err();
+        assert!(matches!(&errors[..], [CompileError::Syntax { expected, .. }] if expected == &["#kthxbye"]), "{:?}", errors);
+    }
+
+    #[test]
+    fn tag_map_overrides_one_element() {
+        let mut tag_map = TagMap::new();
+        tag_map.apply_line("paragraf=para", 1);
+        tag_map.apply_line("bold=9b", 2);
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.tag_map = tag_map;
+        let html = compiler.compile_to_html(&page("#maek paragraf #gimmeh bold hi #mkay #oic")).unwrap();
+        assert!(html.contains("<para><b>hi</b></para>"), "{}", html);
+    }
+
+    #[test]
+    fn shorthand_compiles_to_the_same_html() {
+        let regular = compile_str("#hai #maek head #gimmeh title t #mkay #oic #maek paragraf hello #gimmeh bold world #mkay #oic #kthxbye").unwrap();
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.shorthand = true;
+        let shorthand = compiler.compile_to_html("#hai #head #title t #/title #/head #p hello #b world #/b #/p #kthxbye").unwrap();
+        assert_eq!(shorthand, regular);
+    }
+
+    #[test]
+    fn shorthand_errors_name_the_alias_as_written() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.shorthand = true;
+        let errors = compiler.compile_to_html("#hai #head #title t #/title #/head #li x #/li #kthxbye").unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Syntax { found, .. } if found == "#li"), "{:?}", errors);
+    }
+
+    #[test]
+    fn source_comments_give_the_line_of_each_block() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.source_comments = true;
+        let html = compiler
+            .compile_to_html("#hai #maek head #gimmeh title t #mkay #oic\n#maek list\n#gimmeh item a #mkay #oic\n#maek paragraf b #oic #kthxbye")
+            .unwrap();
+        assert!(
+            html.contains("    <!-- src:2 -->\n    <ul>\n      <li>a</li>\n    </ul>\n    <!-- src:4 -->\n    <p>b</p>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn javascript_urls_are_rejected() {
+        let errors = LolcodeCompiler::new()
+            .check("#hai #maek head #gimmeh title t #mkay #oic\n#maek paragraf #gimmeh linkz javascript:x hi #mkay #oic #kthxbye")
+            .unwrap_err();
+        assert_eq!(
+            errors.iter().map(|error| error.to_string()).collect::<Vec<_>>(),
+            vec!["Semantic error at line 2: URL 'javascript:x' uses scheme 'javascript:' which is not allowed (allowed: http, https, data)."]
+        );
+    }
+
+    #[test]
+    fn allowed_schemes_replace_the_default_list() {
+        let source = page("#gimmeh soundz ftp://a.com/x.mp3 #mkay");
+        assert!(compile_str(&source).is_err());
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.allowed_schemes = vec!["ftp".to_string()];
+        assert!(compiler.compile_to_html(&source).is_ok());
+    }
+
+    #[test]
+    fn invalid_utf8_reports_its_byte_offset() {
+        let path = std::env::temp_dir().join(format!("lolcode_invalid_utf8_{}.lol", process::id()));
+        fs::write(&path, b"#hai caf\xe9 #kthxbye").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let error = read_source(path_str, None).unwrap_err();
+        assert_eq!(error, "invalid UTF-8 at byte 8. If the file is Latin-1 encoded, try --input-encoding latin1");
+        assert_eq!(read_source(path_str, Some("latin1")).unwrap(), "#hai caf\u{e9} #kthxbye");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn comments_are_kept_or_stripped() {
+        let source = page("#obtw a note #tldr #maek paragraf text #oic");
+        let html = compile_str(&source).unwrap();
+        assert!(html.contains("<!-- a note -->"), "{}", html);
+
+        let config = match Config::build(&args(&["page.lol", "--comments", "strip"])) {
+            Ok(Action::Compile(config)) => config,
+            _ => panic!("--comments strip should build a config"),
+        };
+        let mut compiler = LolcodeCompiler::new();
+        compiler.strip_comments = config.strip_comments;
+        let html = compiler.compile_to_html(&source).unwrap();
+        assert!(!html.contains("<!--") && html.contains("<p>text</p>"), "{}", html);
+    }
+
+    #[test]
+    fn nested_comments_stay_in_one_comment() {
+        let html = compile_str(&page("#obtw a #obtw b #tldr c #tldr #maek paragraf x #oic")).unwrap();
+        assert!(html.contains("<!-- a #obtw b #tldr c -->\n    <p>x</p>"), "{}", html);
+    }
+
+    #[test]
+    fn nested_comment_needs_a_tldr_for_each_level() {
+        let error = compile_str(&page("#obtw a #obtw b #tldr c")).unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { expected, found, .. } if expected == &["#tldr"] && found == "#kthxbye"), "{:?}", error);
+    }
+
+    #[test]
+    fn variables_resolve_by_name() {
+        let html = compile_str(&page(
+            "#i haz x #it iz one #mkay #i haz y #it iz two #mkay #maek paragraf #lemme see x #mkay #lemme see y #mkay #oic",
+        ))
+        .unwrap();
+        assert!(html.contains("<p>one two</p>"), "{}", html);
+    }
+
+    #[test]
+    fn inner_scope_shadows_a_variable_of_the_same_name() {
+        let html = compile_str(&page(
+            "#i haz x #it iz outer #mkay #maek paragraf #i haz x #it iz inner #mkay #lemme see x #mkay #oic #maek paragraf #lemme see x #mkay #oic",
+        ))
+        .unwrap();
+        assert!(html.contains("<p>inner</p>\n    <p>outer</p>"), "{}", html);
+    }
+
+    #[test]
+    fn text_and_variable_values_are_escaped() {
+        let html = compile_str(&page("#i haz tag #it iz <b> #mkay #maek paragraf 5 < 10 & \"quoted\" #lemme see tag #mkay #oic")).unwrap();
+        assert!(html.contains("<p>5 &lt; 10 &amp; &quot;quoted&quot; &lt;b&gt;</p>"), "{}", html);
+        assert_eq!(escape_html("it's <a & b>"), "it&#39;s &lt;a &amp; b&gt;");
+    }
+
+    #[test]
+    fn video_iframe_is_closed() {
+        let html = compile_str(&page("#gimmeh vidz http://example.com/v #mkay #maek paragraf after #oic")).unwrap();
+        assert!(html.contains("<iframe src=\"http://example.com/v\"></iframe>\n    <p>after</p>"), "{}", html);
+    }
+
+    #[test]
+    fn video_src_is_quoted_and_escaped() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.document = Some(HtmlNode::Document(vec![HtmlNode::Video("v.mp4\" onload=\"x".to_string())]));
+        let html = compiler.to_html().unwrap();
+        assert!(html.contains("<iframe src=\"v.mp4&quot; onload=&quot;x\"></iframe>"), "{}", html);
+    }
+
+    #[test]
+    fn parse_builds_the_document_tree() {
+        let compiler = checked(
+            "#hai #obtw note #tldr #maek head #gimmeh title My #mkay #oic\n#i haz x #it iz page #mkay\n\
+             #maek paragraf Hi #gimmeh bold there #mkay #lemme see x #mkay #gimmeh newline #oic\n\
+             #maek list #gimmeh item one #mkay #oic\n#gimmeh soundz a.mp3 #mkay #gimmeh vidz b.mp4 #mkay\n#kthxbye",
+        );
+        let text = |text: &str| HtmlNode::Text(text.to_string());
+        assert_eq!(
+            compiler.document,
+            Some(HtmlNode::Document(vec![
+                HtmlNode::Comment("note".to_string()),
+                HtmlNode::Head { line: 1, children: vec![HtmlNode::Title(vec![text("My")])] },
+                HtmlNode::Paragraph {
+                    line: 3,
+                    children: vec![
+                        text("Hi"),
+                        HtmlNode::Bold(vec![text("there")]),
+                        HtmlNode::VarUse { name: "x".to_string(), value: Some("page".to_string()), line: 3 },
+                        HtmlNode::Newline,
+                    ],
+                },
+                HtmlNode::List { line: 4, children: vec![HtmlNode::Item(vec![text("one")])] },
+                HtmlNode::Audio(vec!["a.mp3".to_string()]),
+                HtmlNode::Video("b.mp4".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn to_html_renders_a_constructed_tree() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.document = Some(HtmlNode::Document(vec![HtmlNode::List {
+            line: 1,
+            children: vec![HtmlNode::Item(vec![HtmlNode::Italics(vec![HtmlNode::Text("one".to_string())])])],
+        }]));
+        let html = compiler.to_html().unwrap();
+        assert!(html.contains("<ul>\n      <li><i>one</i></li>\n    </ul>"), "{}", html);
+    }
+
+    #[test]
+    fn bighead_is_an_h1() {
+        let html = compile_str(&page("#maek bighead Welcome #oic")).unwrap();
+        assert!(html.contains("<h1>Welcome</h1>"), "{}", html);
+    }
+
+    #[test]
+    fn link_escapes_its_address_and_text() {
+        let html = compile_str(&page("#gimmeh linkz http://example.com/a_b.html a & b #mkay")).unwrap();
+        assert!(html.contains("<a href=\"http://example.com/a_b.html\">a &amp; b</a>"), "{}", html);
+    }
+
+    #[test]
+    fn link_without_an_address_is_an_error() {
+        let error = compile_str(&page("#gimmeh linkz #mkay")).unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { expected, found, .. } if expected == &["address"] && found == "#mkay"), "{:?}", error);
+    }
+
+    #[test]
+    fn every_syntax_error_is_reported() {
+        let errors = LolcodeCompiler::new()
+            .check("#hai #maek head #gimmeh title t #mkay #oic\n#i name #it iz v #mkay\n#gimmeh itaclis y #mkay\n#maek list #gimmeh item a #mkay\n#kthxbye")
+            .unwrap_err();
+        let found: Vec<(usize, &str)> = errors
+            .iter()
+            .map(|error| match error {
+                CompileError::Syntax { line, found, .. } => (*line, found.as_str()),
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(found, vec![(2, "name"), (3, "itaclis"), (5, "#kthxbye")]);
     }
 
-    /**
-     * Task 4 - HTML Conversion - convert the syntactically and semantically valid lolcode into HTML
-     */
-    fn to_html(&mut self) -> String{
+    #[test]
+    fn syntax_error_points_at_the_column_of_the_token() {
+        let error = compile_str("#hai #maek head #gimmeh title t #mkay #oic\n#maek paragraf hi #gimmeh bolt x #mkay #oic #kthxbye").unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { line: 2, column: 27, found, .. } if found == "bolt"), "{:?}", error);
+        assert!(error.to_string().starts_with("Syntax error at line 2, column 27:"), "{}", error);
+    }
 
-        // Define a scope stack to support resolution of variables
-        let mut scope_stack: Vec<VariableInfo> = Vec::new(); 
+    #[test]
+    fn compile_str_returns_the_page() {
+        let html = compile_str("#hai #maek head #gimmeh title Hi #mkay #oic #maek paragraf hello #oic #kthxbye").unwrap();
+        assert_eq!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n  <head>\n    <meta charset=\"utf-8\">\n    <title>Hi</title>\n  </head>\n  <body>\n    <p>hello</p>\n  </body>\n</html>\n"
+        );
+        assert!(matches!(compile_str("#hai #maek paragraf hello"), Err(CompileError::Syntax { expected, .. }) if expected == ["head"]));
+    }
 
-        // HTML code conversion, get a copy of tokens from the compiler for HTML conversion, already validated
-        let tokens = &self.language_tokens;
+    // The config of a compile run, for the tests of the flags
+    fn config(arguments: &[&str]) -> Config {
+        match Config::build(&args(arguments)) {
+            Ok(Action::Compile(config)) => *config,
+            _ => panic!("{:?} is not a compile run", arguments),
+        }
+    }
 
-        // collect the token strings
-        let mut token_strings: Vec<String> =
-            tokens.iter().map(|(token, _line)| token.clone()).collect();
+    #[test]
+    fn no_open_keeps_the_file_argument() {
+        let opened = config(&["page.lol"]);
+        assert!(opened.open_browser && opened.file_path == "page.lol");
+        for arguments in [["--no-open", "page.lol"], ["page.lol", "--no-open"]] {
+            let config = config(&arguments);
+            assert!(!config.open_browser && config.file_path == "page.lol");
+        }
+    }
 
+    #[test]
+    fn output_path_must_be_html() {
+        let config = config(&["-o", "out.html", "input.lol"]);
+        assert_eq!(config.output_path.as_deref(), Some("out.html"));
+        assert_eq!(config.file_path, "input.lol");
+        assert!(matches!(Config::build(&args(&["--output", "out.txt", "input.lol"])), Err(message) if message.contains(".html")));
+    }
 
-        //Initialize an empty html string
-        let mut html_string: String = " ".to_string();
+    #[test]
+    fn file_url_of_unix_and_windows_paths() {
+        assert_eq!(file_url(Path::new("/home/me/page.html")).unwrap(), "file:///home/me/page.html");
+        assert_eq!(file_url(Path::new(r"\\?\C:\dir\page.html")).unwrap(), "file:///C:/dir/page.html");
+        assert_eq!(file_url(Path::new(r"C:\dir\page.html")).unwrap(), "file:///C:/dir/page.html");
+    }
 
-        //Get the first token
-        while let Some(token) = token_strings.pop() {
-            // If the first token is #hai, append DOCTYPE and starting html tags
-            if token.to_lowercase() == "#hai" {
-                html_string.push_str("<!DOCTYPE html> \n<html>");
-                continue;
+    #[test]
+    fn browser_error_message_names_the_html_file() {
+        let error = io::Error::new(io::ErrorKind::NotFound, "no browser found");
+        assert_eq!(
+            browser_error_message(Path::new("out/page.html"), &error),
+            "Error opening the browser: no browser found. The html file was written to out/page.html, open it manually."
+        );
+    }
 
-                
-            }
-
-            //If there is variable initialization, push the appropriate value into stack
-            //If the next is #I
-            if token.to_lowercase() == "#i" {
-                //Pop next token
-                if let Some(token) = token_strings.pop()  {
-                    //Expect haz
-                    if token.to_lowercase() == "haz"
-                    {
-                //Pop next token
-                            if let Some(next_token) = token_strings.pop()
-                        {
-                            //Expect variable name
-                            let var_name = next_token; 
-
-                            //Pop next token
-                            if let Some(next_token) = token_strings.pop()
-                            {
-                                //Expect #it
-                                if next_token.to_lowercase() == "#it"
-                                {
-                                    //Pop next token
-                                    if let Some(next_token_iz) = token_strings.pop()
-                                    {
-                                        //Expect iz
-                                        if next_token_iz.to_lowercase() == "iz"
-                                        {
-                                            //Pop next token
-                                            if let Some(value_token) = token_strings.pop()
-                                            {
-                                                //Variable value
-                                                let var_value = value_token; 
-
-                                                if let Some(mkay_value) = token_strings.pop()
-                                                {
-                                                    if mkay_value.to_lowercase() == "#mkay"
-                                                    {
-                                                        //Declare stack, append name and value of variable to VariableInfo structure
-                                                        let variable_info = VariableInfo {
-                                                            name: var_name,
-                                                            value: Some(var_value),
-                                                            line_defined: 0,
-                                                        };
-
-                                                        //Push the variable to the scope stack
-                                                        scope_stack.push(variable_info);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    #[test]
+    fn list_in_paragraph_is_indented() {
+        let html = compile_str(&page("#maek paragraf intro #maek list #gimmeh item a #mkay #gimmeh item b #mkay #oic #oic")).unwrap();
+        let body = "  <body>\n    <p>\n      intro\n      <ul>\n        <li>a</li>\n        <li>b</li>\n      </ul>\n    </p>\n  </body>\n";
+        assert!(html.starts_with("<!DOCTYPE html>\n") && html.contains(body), "{}", html);
+    }
 
-            // If the token is #kthxbye, append ending html tag
-            if token.to_lowercase() == "#kthxbye" {
-                html_string.push_str("\n</html>");
-                break;
-            }
+    #[test]
+    fn unused_variable_is_a_warning() {
+        let unused = checked(&page("#i haz x #it iz 1 #mkay #maek paragraf hi #oic"));
+        assert_eq!(unused.warnings, vec!["variable 'x' defined at line 1 is never used.".to_string()]);
 
-            //If the token is #obtw, add html comments
-            if token.to_lowercase() == "#obtw" {
-                html_string.push_str("\n<!--");
-                //Consume text tokens
-                while let Some(comment_token) = token_strings.pop() {
-                    // End when #tldr is found 
-                    if comment_token.to_lowercase() == "#tldr" {
-                        html_string.push_str(" -->\n");
-                        break;
-                    }
+        let used = checked(&page("#i haz x #it iz 1 #mkay #maek paragraf #lemme see x #mkay #oic"));
+        assert!(used.warnings.is_empty(), "{:?}", used.warnings);
+    }
 
-                    //push comments to html string
-                    html_string.push_str(" ");
-                    html_string.push_str(&comment_token);
-                }
-            }
+    #[test]
+    fn variable_value_keeps_every_word() {
+        let html = compile_str(&page("#i haz greeting #it iz Hello World #mkay #maek paragraf #lemme see greeting #mkay #oic")).unwrap();
+        assert!(html.contains("<p>Hello World</p>"), "{}", html);
+    }
 
-            // if the token is maek
-            if token.to_lowercase() == "#maek" {
-                //Pop next token
-                if let Some(next_token) = token_strings.pop() {
-                    //if next token is head, append head
-                    if next_token.to_lowercase() == "head" {
-                        html_string.push_str("\n<head>");
-
-                        //append end tag of head
-                        while let Some(head_token) = token_strings.pop() {
-                            if head_token.to_lowercase() == "#oic" {
-                                html_string.push_str("</head>\n");
-                                break;
-                            }
-
-                            //if next token is #gimmeh, append title tag
-                            if head_token.to_lowercase() == "#gimmeh" {
-                                if let Some(title_token) = token_strings.pop() {
-                                    if title_token.to_lowercase() == "title" {
-                                        html_string.push_str("\n<title>");
-                                        
-                                        //consume text tokens for title
-                                        while let Some(text_token) = token_strings.pop() {
-
-                                            //append end tag of title when mkay is found
-                                            if text_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str("</title>\n");
-                                                break;
-                                            }
-
-                                            //Push title to html string
-                                            html_string.push_str(" ");
-                                            html_string.push_str(&text_token);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    #[test]
+    fn token_stream_peek_does_not_advance() {
+        let mut stream = TokenStream::new();
+        stream.push(("#hai".to_string(), 1, 1), 0..4);
+        stream.push(("#kthxbye".to_string(), 1, 6), 5..13);
+
+        assert_eq!(stream.peek().map(|token| token.0.as_str()), Some("#hai"));
+        assert_eq!(stream.peek().map(|token| token.0.as_str()), Some("#hai"));
+        assert_eq!(stream.position(), 0);
+
+        assert_eq!(stream.next_token(), Some(("#hai".to_string(), 1, 1)));
+        assert_eq!(stream.position(), 1);
+        assert_eq!(stream.peek().map(|token| token.0.as_str()), Some("#kthxbye"));
+        assert_eq!(stream.next_token(), Some(("#kthxbye".to_string(), 1, 6)));
+        assert_eq!(stream.next_token(), None);
+    }
 
-                    //If the next element found is paragraf, append starting paragraph tag
-                    if next_token.to_lowercase() == "paragraf" {
-                        html_string.push_str("\n<p>");
-
-                        //Consume text tokens in paragraph
-                        while let Some(para_token) = token_strings.pop() {
-
-                            //if end of paragraph is hit, append ending p tag
-                            if para_token.to_lowercase() == "#oic" {
-                                html_string.push_str("</p>\n");
-
-                                //If there is any local scope, pop out of the scope stack
-                            if scope_stack.len() > 1
-                            {
-                                scope_stack.pop();
-                            }
-                                break;
-                            }
-
-                            //If there is variable declaration, expect #i
-                             if para_token.to_lowercase() == "#i" {
-                if let Some(token) = token_strings.pop()  {
-
-                    //Expect haz
-                    if token.to_lowercase() == "haz"
-                    {
-                        //Expect variable name
-                        if let Some(next_token) = token_strings.pop()
-                        {
-                            let var_name = next_token; 
-
-                            if let Some(next_token) = token_strings.pop()
-                            {
-                                //Expect #it
-                                if next_token.to_lowercase() == "#it"
-                                {
-                                    if let Some(next_token_iz) = token_strings.pop()
-                                    {
-                                        //Expect iz
-                                        if next_token_iz.to_lowercase() == "iz"
-                                        {
-                                            if let Some(value_token) = token_strings.pop()
-                                            {
-                                                //Expect Variable value
-                                                let var_value = value_token; 
-
-                                                if let Some(mkay_value) = token_strings.pop()
-                                                {
-                                                    //Expect mkay value
-                                                    if mkay_value.to_lowercase() == "#mkay"
-                                                    {
-                                                        //Declare stack with VariableInfo using name and value
-                                                        let variable_info = VariableInfo {
-                                                            name: var_name,
-                                                            value: Some(var_value),
-                                                            line_defined: 0,
-                                                        };
-
-                                                        //Push the variable to the scope stack
-                                                        scope_stack.push(variable_info);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    #[test]
+    fn strict_mode_lists_the_valid_tags() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.strict = true;
+        let errors = compiler.check(&page("#bogus")).unwrap_err();
+        assert!(
+            matches!(&errors[0], CompileError::UnknownTag { token, valid, .. } if token == "#bogus" && valid.contains(&"#hai".to_string())),
+            "{:?}",
+            errors
+        );
+        assert!(errors[0].to_string().contains("unknown tag '#bogus', the valid tags are [#hai, #kthxbye,"), "{}", errors[0]);
 
-            // IF there is variable usage, expect #lemme
-                if para_token.to_lowercase() == "#lemme"
-                                            {
-                                                if let Some(variable_lemme) = token_strings.pop()
-                                                {
-                                                    //Expect see after #lemme
-                                                    if variable_lemme.to_lowercase() == "see"
-                                                    {
-                                                        //Expect variable_name after see
-                                                        if let Some(variable_name) = token_strings.pop()
-                                                        {
-                                                            let variable = scope_stack.last().unwrap(); 
-
-                                                            //Push the variable value to the html_string
-                                                            let value = variable.value.clone(); 
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(&value.unwrap());
-                                                        }
-                                                        
-                                                    }
-
-                                                    continue;
-                                                }
-                                            }
-
-                            //If there is maek tag, there can be a list as per the grammar inside paragraf
-                            //expect #maek
-                            if para_token.to_lowercase() == "#maek" {
-                                if let Some(list_token) = token_strings.pop() {
-                                    
-                                    //append the ul tag if list is found
-                                    if list_token.to_lowercase() == "list" {
-
-                                        
-                                        html_string.push_str("\n<ul>");
-
-                                        //consume list elements 
-                                        while let Some(list_elem_token) = token_strings.pop() {
-                                        
-                                        //End of list found, append it to html_string
-                                            if list_elem_token.to_lowercase() == "#oic" {
-                                                html_string.push_str("\n</ul>\n");
-                                                break;
-                                            }
-
-
-                                            //If #gimmeh is found, it is list item
-                                            if list_elem_token.to_lowercase() == "#gimmeh" {
-                                                if let Some(item_token) = token_strings.pop() {
-
-                                                    //Append li to html_string for list item
-                                                    if item_token.to_lowercase() == "item" {
-                                                        html_string.push_str("\n<li>");
-
-                                                        //consume text tokes
-                                                        while let Some(item_content_token) =
-                                                                token_strings.pop()
-                                                                
-                                                        {
-
-
-                                                            // If variable usage is found, expect lemme
-                                                            if item_content_token.to_lowercase() == "#lemme"
-                                            {
-                                                // expect see
-                                                if let Some(variable_lemme) = token_strings.pop()
-                                                {
-                                                    if variable_lemme.to_lowercase() == "see"
-                                                    {
-                                                        //Add the value of the variable from the scope stacl
-                                                        if let Some(variable_name) = token_strings.pop()
-                                                        {
-                                                            let variable = scope_stack.last().unwrap(); 
-
-
-                                                            //Push the value of the variable to the scope stack
-                                                            let value = variable.value.clone(); 
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(&value.unwrap());
-                                                        }
-                                                        
-                                                    }
-
-                                                    continue;
-                                                }
-                                            }
-                                                    //end of list item, append </li> tag, and push it to html string
-                                                            if item_content_token
-                                                                .to_lowercase()
-                                                                == "#mkay"
-                                                            {
-                                                                html_string.push_str(
-                                                                    "</li>\n",
-                                                                );
-                                                                break;
-                                                            }
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(
-                                                                &item_content_token,
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-
-                            //IF there is gimmeh
-                            if para_token.to_lowercase() == "#gimmeh" {
-                                if let Some(para_elem_token) = token_strings.pop() {
-
-                                    // if there is newline, expect newline and append <br/>
-                                    if para_elem_token.to_lowercase() == "newline" {
-                                        html_string.push_str("\n<br/>\n");
-                                    }
-
-                                    // if there is newline, expect soundz and append <audio controls>
-                                    if para_elem_token.to_lowercase() == "soundz" {
-                                        if let Some(address_token) = token_strings.pop() {
-                                            html_string.push_str(&format!(
-                                                "\n<audio controls>\n<source src=\"{}\" type=\"audio/mpeg\">",
-                                                address_token
-                                            ));
-                                        }
-
-                                        //Append URL address of audio
-                                        if let  Some(address_token) = token_strings.pop() {
-
-                                            //Append audio end tag
-                                            if address_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str("</audio>\n");
-                                            }
-                            
-                                        } 
-                                    }
-    
-                                    
-                                    //if the element found is video
-                                    if para_elem_token.to_lowercase() == "vidz" 
-                                    {
-                                        //Append iframe src tag
-                                        if let Some(address_token) = token_strings.pop() {
-                                            html_string.push_str(&format!(
-                                                "\n<iframe src = {}>\n",
-                                                address_token
-                                            ));
-                                        }
-
-
-                                        //Append the URL address of the video, consume till end of the tag is found
-                                        if let  Some(address_token) = token_strings.pop() {
-                                            if address_token.to_lowercase() == "#mkay" {
-                                                continue;
-                                            }
-                            
-                                        }
-                                    }
-
-                                    // else if the paragraf element found is bold, add starting bold tag
-                                    if para_elem_token.to_lowercase() == "bold" {
-                                        html_string.push_str(" <b>");
-
-                                        //If variable usage is found, append the value of the variable
-                                        while let Some(bold_token) = token_strings.pop() {
-                                            //Expect #lemme
-                                            if bold_token.to_lowercase() == "#lemme"
-                                            {
-                                                if let Some(variable_lemme) = token_strings.pop()
-                                                {
-                                                    //Expect see
-                                                    if variable_lemme.to_lowercase() == "see"
-                                                    {
-                                                        if let Some(variable_name) = token_strings.pop()
-                                                        {
-                                                            //Find the value of variable and append it to the html string
-                                                            let variable = scope_stack.last().unwrap(); 
-
-                                                            let value = variable.value.clone(); 
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(&value.unwrap());
-                                                        }
-                                                        
-                                                    }
-
-                                                    continue;
-                                                }
-                                            }
-
-
-                                            //end of bold input, append, ending bold tag
-                                            if bold_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str(" </b>");
-                                                break;
-                                            }
-                                            html_string.push_str(" ");
-                                            html_string.push_str(&bold_token);
-                                        }
-                                    }
-
-                                    //If the paragraf element found is italics, append starting italics tag
-                                    if para_elem_token.to_lowercase() == "italics" {
-                                        html_string.push_str(" <i>");
-                                        while let Some(bold_token) = token_strings.pop() {
-
-                                            //consume text tokens and append closing italics tag at the end
-                                            if bold_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str(" </i>");
-                                                break;
-                                            }
-                                            html_string.push_str(" ");
-                                            html_string.push_str(&bold_token);
-                                        }
-                                    }
-
-
-
-                                }
-
-                                //If no matches found, consume all text elements (without #)
-                            } else if !para_token.starts_with("#") {
-                                html_string.push_str(" ");
-                                html_string.push_str(&para_token);
-                            }
-
-                        
-                    }
+        let errors = LolcodeCompiler::new().check(&page("#bogus")).unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Lexical { token, .. } if token == "#bogus"), "{:?}", errors);
+    }
 
-                }
+    #[test]
+    fn description_is_a_meta_tag_of_the_head() {
+        let html = compile_str("#hai #maek head #gimmeh title T #mkay #gimmeh describ A & B #mkay #oic #kthxbye").unwrap();
+        assert!(html.contains("<title>T</title>\n    <meta name=\"description\" content=\"A &amp; B\">"), "{}", html);
+    }
 
-                       
-                }
+    #[test]
+    fn description_is_allowed_once_and_only_in_the_head() {
+        let error = compile_str("#hai #maek head #gimmeh describ a #mkay\n#gimmeh describ b #mkay #oic #kthxbye").unwrap_err();
+        assert!(
+            matches!(&error, CompileError::Structure { line: 2, message } if message.contains("only one describ") && message.ends_with("at line 1.")),
+            "{:?}",
+            error
+        );
 
-                    
-            }
-
-            else  {
-
-                //If the token found is variable usage, expect #lemme
-                if token.to_lowercase() == "#lemme"
-                                            {
-
-                                                //Expect see
-                                                if let Some(variable_lemme) = token_strings.pop()
-                                                {
-                                                    if variable_lemme.to_lowercase() == "see"
-                                                    {
-
-                                                        //Find the value of the variable, and append its value to the string
-                                                        if let Some(variable_name) = token_strings.pop()
-                                                        {
-                                                            let variable = scope_stack.last().unwrap(); 
-
-                                                            let value = variable.value.clone(); 
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(&value.unwrap());
-                                                        }
-                                                        
-                                                    }
-
-                                                    continue;
-                                                }
-                                            }
-
-                                            // If the given token is gimmeh, 
-               if token.to_lowercase() == "#gimmeh" {
-
-                    //If there is newline tag, append <br> to the html string
-                                if let Some(para_elem_token) = token_strings.pop() {
-                                    if para_elem_token.to_lowercase() == "newline" {
-                                        html_string.push_str("\n<br/>\n");
-                                    }
-
-                        //If there is soundz tag, append <audio controls> to the html string
-
-                                    if para_elem_token.to_lowercase() == "soundz" {
-                                        if let Some(address_token) = token_strings.pop() {
-                                            html_string.push_str(&format!(
-                                                "\n<audio controls>\n<source src=\"{}\" type=\"audio/mpeg\">",
-                                                address_token
-                                            ));
-                                        }
-
-                                        //consume URL address till end tag; append tag
-                                        if let  Some(address_token) = token_strings.pop() {
-                                            if address_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str("</audio>\n");
-                                            }
-                            
-                                        } 
-                                    }
-    
-                                        //If there is vidzoundz tag, append <iframe src> to the html string
-                                    if para_elem_token.to_lowercase() == "vidz" 
-                                    {
-                                        if let Some(address_token) = token_strings.pop() {
-                                            html_string.push_str(&format!(
-                                                "\n<iframe src = {}>\n",
-                                                address_token
-                                            ));
-                                        }
-
-                                        //Get URL address
-                                        if let  Some(address_token) = token_strings.pop() {
-                                            if address_token.to_lowercase() == "#mkay" {
-                                                continue;
-                                            }
-                            
-                                        }
-                                    }
-
-                                    //If bold is found, consume bold elements
-                                    if para_elem_token.to_lowercase() == "bold" {
-                                        html_string.push_str(" <b>");
-                                        while let Some(bold_token) = token_strings.pop() {
-
-                                            // If variable usage, found expect #Lemme
-                                            if bold_token.to_lowercase() == "#lemme"
-                                            {
-                                                if let Some(variable_lemme) = token_strings.pop()
-                                                {
-                                                    //Expect see
-                                                    if variable_lemme.to_lowercase() == "see"
-                                                    {
-                                                        if let Some(variable_name) = token_strings.pop()
-                                                        {
-                                                            //Append the value of the variable to the value
-                                                            let variable = scope_stack.last().unwrap(); 
-
-                                                            let value = variable.value.clone(); 
-                                                            html_string.push_str(" ");
-                                                            html_string.push_str(&value.unwrap());
-                                                        }
-                                                        
-                                                    }
-
-                                                    continue;
-                                                }
-                                            }
-
-                                            //Add the ending bold tag
-                                            if bold_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str(" </b>");
-                                                break;
-                                            }
-                                            html_string.push_str(" ");
-                                            html_string.push_str(&bold_token);
-                                        }
-                                    }
-
-                                    //If italics is found, append <i> tag
-
-                                    if para_elem_token.to_lowercase() == "italics" {
-                                        html_string.push_str(" <i>");
-
-                                        //consume text tokens and append </i> tags
-                                        while let Some(bold_token) = token_strings.pop() {
-                                            if bold_token.to_lowercase() == "#mkay" {
-                                                html_string.push_str(" </i>");
-                                                break;
-                                            }
-                                            html_string.push_str(" ");
-                                            html_string.push_str(&bold_token);
-                                        }
-                                    }
-
-
-
-                                }
-
-                            }
-
-                //If any text tokens (non-tags) are found, push it to the html tokens
-                else if !token.starts_with("#")
-                {
-                    html_string.push_str(" ");
-                    html_string.push_str(&token);               
-                }
+        let error = compile_str(&page("#gimmeh describ a #mkay")).unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { found, .. } if found == "describ"), "{:?}", error);
+    }
 
-                //for any tag keywords without hash-tags, skip them 
-                else  {
-                   continue;
-                }
+    #[test]
+    fn grid_has_a_row_per_roe_and_a_cell_per_sel() {
+        let html = compile_str(&page(
+            "#maek grid #maek roe #gimmeh sel a #mkay #gimmeh sel b #mkay #oic #maek roe #gimmeh sel c #mkay #gimmeh sel d #mkay #oic #oic",
+        ))
+        .unwrap();
+        assert_eq!(html.matches("<table>").count(), 1, "{}", html);
+        assert_eq!(html.matches("<tr>").count(), 2, "{}", html);
+        assert_eq!(html.matches("</tr>").count(), 2, "{}", html);
+        assert_eq!(html.matches("<td>").count(), 4, "{}", html);
+        assert!(html.contains("<td>a</td>") && html.contains("<td>d</td>"), "{}", html);
+    }
 
-            }
-        }
+    #[test]
+    fn custom_head_start_keyword_parses() {
+        let keywords = KeywordConfig { head_start: vec!["#start".into()], ..KeywordConfig::default() };
+        let lexer = LolcodeLexicalAnalyzer::with_keywords("", keywords.clone());
+        assert!(lexer.lookup("#start") && !lexer.lookup("#hai"));
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.keywords = keywords;
+        let html = compiler.compile_to_html("#start #maek head #gimmeh title t #mkay #oic #maek paragraf hi #oic #kthxbye").unwrap();
+        assert!(html.contains("<p>hi</p>"), "{}", html);
+        assert!(compiler.compile_to_html(&page("")).is_err());
+    }
 
-        //return html string
-             html_string
+    #[test]
+    fn balance_checker_flags_malformed_html() {
+        assert!(unbalanced_tags("<!DOCTYPE html>\n<p>a<br/><meta charset=\"utf-8\"></p>\n<!-- <b> -->").is_empty());
+        assert_eq!(unbalanced_tags("<p>\n<iframe src=\"v\">\n</p>"), vec!["line 3: </p> closes <iframe> opened at line 2.", "line 1: <p> is never closed."]);
+        assert_eq!(unbalanced_tags("<b>x</b></i>"), vec!["line 1: </i> has no opening tag."]);
+    }
 
+    #[test]
+    fn shadowing_can_be_disallowed() {
+        let source = page("#i haz x #it iz outer #mkay\n#maek paragraf #i haz x #it iz inner #mkay #lemme see x #mkay #oic");
+        let html = compile_str(&source).unwrap();
+        assert!(html.contains("<p>inner</p>"), "{}", html);
+
+        let mut compiler = LolcodeCompiler::new();
+        compiler.allow_shadowing = false;
+        let errors = compiler.check(&source).unwrap_err();
+        assert!(
+            matches!(&errors[0], CompileError::Semantic { line: 2, message }
+                if message == "Variable 'x' declared at line 2 shadows the variable defined at line 1 in an outer scope."),
+            "{:?}",
+            errors
+        );
     }
 
-}
+    #[test]
+    fn digit_values_are_numbers() {
+        assert_eq!(classify_value(Some("42")), VarKind::Number(42));
+        assert_eq!(classify_value(Some("4x2")), VarKind::Text);
+        assert_eq!(classify_value(Some("99999999999999999999999")), VarKind::Text);
+        assert_eq!(classify_value(None), VarKind::Text);
+    }
 
+    #[test]
+    fn maths_emits_the_sum() {
+        let html = compile_str(&page("#i haz a #it iz 2 #mkay #i haz b #it iz 40 #mkay #maek paragraf #gimmeh maths a b #mkay #oic")).unwrap();
+        assert!(html.contains("<p>42</p>"), "{}", html);
 
-// Implementation for the LolCodeCompiler
-impl Compiler for LolcodeCompiler {
+        let error = compile_str(&page("#i haz a #it iz 2 #mkay #i haz b #it iz two #mkay #maek paragraf #gimmeh maths a b #mkay #oic")).unwrap_err();
+        assert!(matches!(&error, CompileError::Semantic { message, .. } if message.starts_with("Variable 'b' is not a number")), "{:?}", error);
+    }
 
-    //method to start tokenization and getting first token
-    fn compile(&mut self, source: &str) {
+    #[test]
+    #[cfg(windows)]
+    fn first_existing_path_skips_missing_candidates() {
+        let folder = env::temp_dir().join("lolcode_chrome_candidates");
+        fs::create_dir_all(&folder).unwrap();
+        let chrome = folder.join("chrome.exe");
+        fs::write(&chrome, b"").unwrap();
+
+        let candidates = [folder.join("missing.exe"), folder.clone(), chrome.clone()];
+        assert_eq!(first_existing_path(&candidates), Some(chrome.display().to_string()));
+        assert_eq!(first_existing_path(&candidates[..2]), None);
+        fs::remove_dir_all(&folder).unwrap();
+    }
 
-        //Initialize a lexer
-        self.lexer = LolcodeLexicalAnalyzer::new(source);
+    #[test]
+    fn write_html_returns_the_written_path() {
+        let folder = env::temp_dir().join(format!("lolcode_write_html_{}", process::id()));
+        fs::create_dir_all(&folder).unwrap();
 
-        //Tokenize the lexer into tokens
-        self.lexer.tokenize();
+        let written = write_html("<p>hi</p>", &folder.join("page.html")).unwrap();
+        assert!(written.is_absolute() && written.ends_with("page.html"), "{}", written.display());
+        assert_eq!(fs::read_to_string(&written).unwrap(), "<p>hi</p>");
+        fs::remove_dir_all(&folder).unwrap();
+    }
 
-        //Get language tokens - used later for HTML conversion
-        self.language_tokens = self.lexer.tokens.clone();
+    #[test]
+    fn double_hash_is_a_literal_hash() {
+        let html = compile_str(&page("#maek paragraf ##1 best #oic")).unwrap();
+        assert!(html.contains("<p>#1 best</p>"), "{}", html);
+    }
 
-        //Get the first input token 
-        self.start();
+    #[test]
+    fn unicode_text_is_kept() {
+        let html = compile_str("#hai #maek head #gimmeh title Café #mkay #oic #maek paragraf Café ☕ #oic #kthxbye").unwrap();
+        assert!(html.contains("<title>Café</title>") && html.contains("<p>Café ☕</p>"), "{}", html);
+        assert!(LolcodeCompiler::new().check(&page("#maek paragraf a\u{7}b #oic")).is_err());
     }
 
-    //method to lexically analyzer a token
-    fn next_token(&mut self) -> String {
+    #[test]
+    fn check_str_accepts_valid_documents_and_reports_errors() {
+        assert!(check_str(&page("#maek paragraf hi #oic")).is_ok());
 
-        //Pop a token
-        let result = self.lexer.tokens.pop();
+        let error = check_str(&page("#maek paragraf #lemme see nobody #mkay #oic")).unwrap_err();
+        assert!(matches!(&error, CompileError::UnresolvedVariable { name, .. } if name == "nobody"), "{:?}", error);
 
+        let error = check_str("#hai #maek head #gimmeh title t #mkay #oic #maek paragraf hi #kthxbye").unwrap_err();
+        assert!(matches!(&error, CompileError::Unterminated { line: 1, block } if block == "paragraf"), "{:?}", error);
+    }
 
-        //Return a lexeme and its line if it is valid, else through an error
-        if let Some((candidate, line)) = result {
-            self.parser.current_line = line;
+    #[test]
+    fn read_error_message_explains_the_common_cases() {
+        let path = env::temp_dir().join("page.lol");
+        let message = |kind: io::ErrorKind| read_error_message(&io::Error::from(kind), &path);
+        assert_eq!(message(io::ErrorKind::NotFound), format!("file not found: {}", path.display()));
+        assert_eq!(message(io::ErrorKind::PermissionDenied), format!("permission denied, the file cannot be read: {}", path.display()));
+        assert!(message(io::ErrorKind::IsADirectory).ends_with("is a directory, use --dir to compile the .lol files inside it"));
+        assert!(message(io::ErrorKind::TimedOut).starts_with(&format!("{}: ", path.display())));
+    }
 
-            if self.lexer.lookup(&candidate) {
-                self.current_tok = candidate.clone();
-                candidate
-            } else {
-                eprintln!(
-                    "Lexical error at line {}: '{}' is not a recognized token.",
-                    line, candidate
-                );
-                std::process::exit(1);
-            }
-        } 
-        //nothing found, clear current token and initialize new string
-        else {
-            self.current_tok.clear();
-            String::new()
+    #[test]
+    fn token_spans_slice_the_source() {
+        let source = "#hai #maek head #gimmeh title Café #mkay #oic\n#maek paragraf ☕ hi #oic #kthxbye";
+        let mut compiler = LolcodeCompiler::new();
+        compiler.compile(source);
+        let tokens = compiler.lexer.tokens.spanned();
+        for (token, _line, span) in &tokens {
+            assert_eq!(&source[span.clone()], token);
         }
+        assert_eq!(tokens[5], ("Café".to_string(), 1, 30..35));
+        assert_eq!(tokens[11], ("hi".to_string(), 2, 66..68));
     }
 
-    // Start parsing lolcode
-    fn parse(&mut self) {
+    #[test]
+    fn long_documents_do_not_overflow_the_stack() {
+        let words = "word ".repeat(50_000);
+        let items = "#gimmeh item a #mkay ".repeat(10_000);
+        let paragraphs = "#maek paragraf p #oic ".repeat(10_000);
+        let html = compile_str(&page(&format!("{} #maek list {} #oic {}", words, items, paragraphs))).unwrap();
+        assert_eq!(html.matches("word").count(), 50_000);
+        assert_eq!(html.matches("<li>a</li>").count(), 10_000);
+        assert_eq!(html.matches("<p>p</p>").count(), 10_000);
+    }
 
-        //Call lolcode method to start parsing lolcode
-        self.lolcode();
+    #[test]
+    fn json_has_the_title_and_the_variables() {
+        let json = checked("#hai #maek head #gimmeh title My Page #mkay #oic\n#i haz name #it iz Bob #mkay #kthxbye").to_json();
+        assert!(json.starts_with("{\"title\":\"My Page\","), "{}", json);
+        assert!(json.contains("\"variables\":[{\"name\":\"name\",\"value\":\"Bob\",\"line\":2,\"scope\":0}]"), "{}", json);
+    }
 
-        //If no input found, report an error
-        if !self.lexer.tokens.is_empty() {
-            eprintln!(
-                "Syntax error at line {}: Additional tokens found after the document.",
-                self.parser.current_line
-            );
-            std::process::exit(1);
-        }
+    #[test]
+    fn misspelled_tag_suggests_the_closest_one() {
+        let errors = LolcodeCompiler::new().check("#hia #maek head #gimmeh title t #mkay #oic #kthxbye").unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Lexical { token, suggestion: Some(tag), .. } if token == "#hia" && tag == "#hai"), "{:?}", errors);
+        assert!(errors[0].to_string().ends_with("'#hia' is not a recognized token. Did you mean '#hai'?"), "{}", errors[0]);
+
+        let errors = LolcodeCompiler::new().check(&page("#zzzzzzz")).unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Lexical { suggestion: None, .. }), "{:?}", errors);
+        assert_eq!(edit_distance("#hailstorm", "#hai"), 6);
     }
 
-    //Return the clone of current token
-    fn current_token(&self) -> String {
-        self.current_tok.clone()
+    #[test]
+    fn element_keyword_is_not_a_variable_name() {
+        let error = compile_str(&page("#i haz title #it iz t #mkay")).unwrap_err();
+        assert!(
+            matches!(&error, CompileError::Semantic { line: 1, message } if message == "'title' is a reserved keyword and cannot be a variable name."),
+            "{:?}",
+            error
+        );
+        assert!(compile_str(&page("#i haz titles #it iz t #mkay #maek paragraf #lemme see titles #mkay #oic")).is_ok());
     }
 
-    //Set the current token of the compiler
-    fn set_current_token(&mut self, tok: String) {
-        self.current_tok = tok;
+    #[test]
+    fn nested_bold_nests_the_tags() {
+        let html = compile_str(&page("#maek paragraf #gimmeh bold a #gimmeh bold b #mkay #mkay #oic")).unwrap();
+        assert!(html.contains("<p><b>a <b>b</b></b></p>"), "{}", html);
     }
-}
 
-//Custom class to validate a file path or report an error, includes a file path
-struct Config {
-    file_path: String,
-}
+    #[test]
+    fn source_map_points_paragraphs_at_their_lines() {
+        let mut compiler = checked("#hai #maek head #gimmeh title t #mkay #oic\n#maek paragraf one #oic\n\n#maek paragraf two #oic #kthxbye");
+        let (html, map) = compiler.to_html_with_map().unwrap();
+        let line_of = |fragment: &str| {
+            map.iter()
+                .filter(|(range, _line)| html[range.clone()].contains(fragment))
+                .min_by_key(|(range, _line)| range.len())
+                .map(|(_range, line)| *line)
+        };
+        assert_eq!(line_of("<p>one</p>"), Some(2));
+        assert_eq!(line_of("<p>two</p>"), Some(4));
+    }
 
-//implementation for Config
-impl Config {
+    #[test]
+    fn explicit_body_renders_like_the_implicit_one() {
+        let implicit = compile_str(&page("#maek paragraf hi #oic #maek list #gimmeh item a #mkay #oic")).unwrap();
+        let explicit = compile_str(&page("#maek body #maek paragraf hi #oic #maek list #gimmeh item a #mkay #oic #oic")).unwrap();
+        assert_eq!(implicit, explicit);
+        assert_eq!(explicit.matches("<body>").count(), 1, "{}", explicit);
+    }
 
-    //Report an error if no file path is found
-    fn build(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 2 {
-            return Err("not enough arguments, add a file argument");
+    #[test]
+    fn document_scope_survives_extra_pops() {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.push_scope();
+        for _ in 0..3 {
+            compiler.pop_scope();
         }
+        assert_eq!(compiler.scope_stack.len(), 1);
 
-        //return file path from second argument
-        let file_path = args[1].clone();
-
-        //file_path validated, returns OK
-        Ok(Config { file_path })
+        compiler.scope_stack.clear();
+        compiler.declare_variable("x".to_string(), Some("1".to_string()), VarKind::Number(1), 4);
+        assert_eq!(compiler.scope_stack.len(), 1);
+        assert!(compiler.lookup_variable("x").is_some());
+        assert!(compiler.warnings.iter().any(|warning| warning.starts_with("internal error: no variable scope when 'x' was declared at line 4")));
     }
-}
 
+    #[test]
+    fn max_depth_allows_the_limit_and_rejects_one_more() {
+        // A paragraph with bold nested inside it, the paragraph counts as one level
+        let nested_bold = |levels: usize| {
+            page(&format!("#maek paragraf {}x{} #oic", "#gimmeh bold ".repeat(levels - 1), " #mkay".repeat(levels - 1)))
+        };
+        let compile = |levels: usize| {
+            let mut compiler = LolcodeCompiler::new();
+            compiler.max_depth = config(&["--max-depth", "3", "page.lol"]).max_depth.unwrap();
+            compiler.compile_to_html(&nested_bold(levels))
+        };
 
-//Function to open chrome in html
-pub fn open_html_in_chrome<P: AsRef<Path>>(html_file: P) -> io::Result<()> {
+        assert!(compile(2).unwrap().contains("<p><b>x</b></p>"));
+        assert!(compile(3).unwrap().contains("<p><b><b>x</b></b></p>"));
+        let errors = compile(4).unwrap_err();
+        assert!(
+            matches!(&errors[0], CompileError::Structure { line: 1, message }
+                if message == "Maximum nesting depth exceeded, elements can be nested at most 3 deep."),
+            "{:?}",
+            errors
+        );
+        assert!(Config::build(&args(&["--max-depth", "0", "page.lol"])).is_err());
+    }
 
-    //Get the reference of file
-    let p = html_file.as_ref();
-    
+    #[test]
+    fn empty_and_comment_only_titles_keep_their_tags() {
+        for title in ["", "#obtw just a note #tldr"] {
+            let html = compile_str(&format!("#hai #maek head #gimmeh title {} #mkay #oic #kthxbye", title)).unwrap();
+            assert!(html.contains("\n    <title></title>\n"), "{}", html);
+            assert!(unbalanced_tags(&html).is_empty(), "{}", html);
+        }
+    }
 
-    //If path does not exist,  report error
-    if !p.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("File not found: {}", p.display()),
+    #[test]
+    fn declared_variables_include_the_nested_scopes() {
+        let compiler = checked(&page(
+            "#i haz a #it iz 1 #mkay\n#maek boxz #i haz b #it iz two words #mkay\n#maek paragraf #i haz c #mkay #oic #oic\n#i haz d #it iz 4 #mkay",
         ));
-    }
-    
-    //Cannonicalize path to URL 
-    let abs = fs::canonicalize(p)?;
-    
-    // Handle potential non-UTF8 paths gracefully
-    let path_str = abs.to_str().ok_or_else(|| {
-        io::Error::new(io::ErrorKind::InvalidData, "Path contains invalid UTF-8")
-    })?;
-    
+        assert_eq!(
+            compiler.declared_variables(),
+            vec![
+                ("a".to_string(), Some("1".to_string()), 1),
+                ("b".to_string(), Some("two words".to_string()), 2),
+                ("c".to_string(), None, 3),
+                ("d".to_string(), Some("4".to_string()), 4),
+            ]
+        );
 
-    //Get the clean path
-    let clean_path = path_str.strip_prefix(r"\\?\").unwrap_or(path_str);
-    
-    // Convert to file:// URL for chrome display
-    let file_url = format!("file:///{}", clean_path.replace('\\', "/"));
-    
-    
-    // Try to find Chrome from registry if not defined in path
-    if let Some(chrome_path) = find_chrome_path() {
-        return Command::new(chrome_path)
-            .arg(&file_url)
-            .spawn()
-            .map(|_| ())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+        //The scope of each declaration, the document scope is 0
+        let scopes: Vec<usize> = compiler.declarations.iter().map(|(_name, _value, _line, scope)| *scope).collect();
+        assert_eq!(scopes, vec![0, 1, 2, 0]);
     }
-    
-    // Fallback to 'start chrome' command
-    let status = Command::new("cmd")
-        .args(&["/C", "start", "chrome", &file_url])
-        .status()?;
 
-    
-    //Return success or error if chrome is not launced
-    if status.success() {
-        Ok(())
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Could not find or launch Chrome",
-        ))
-    }
-}
 
+    #[test]
+    fn bundle_reads_back_with_every_file() {
+        let folder = env::temp_dir().join(format!("lolcode_bundle_{}", process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let song = folder.join("song.mp3");
+        fs::write(&song, b"ID3 song").unwrap();
+        let bundle = folder.join("page.zip");
 
-//Find chrome path in system 
-#[cfg(windows)]
-fn find_chrome_path() -> Option<String> {
-    // Registry keys where Chrome might be registered
-    let registry_paths = [
-        r"SOFTWARE\Microsoft\Windows\CurrentVersion\App Paths\chrome.exe",
-        r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\App Paths\chrome.exe",
-        r"SOFTWARE\Google\Chrome\BLBeacon",
-    ];
-    
-    //predefinition of how registry will look like
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    
-    // Try each registry path, and associated keys, till a path where chrome exists is found
-    for reg_path in &registry_paths {
-        if let Ok(key) = hklm.open_subkey(reg_path) {
-            // Try to read the default value or "Path" value
-            if let Ok(path) = key.get_value::<String, _>("") {
-                if Path::new(&path).exists() {
-                    return Some(path);
-                }
-            }
-            
-            // Some keys store it in a "version" subkey
-            if let Ok(path) = key.get_value::<String, _>("Path") {
-                let chrome_exe = format!("{}\\chrome.exe", path);
-                if Path::new(&chrome_exe).exists() {
-                    return Some(chrome_exe);
-                }
-            }
-        }
-    }
-    
-    // Also try HKEY_CURRENT_USER by parsing throuhg all the paths
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    for reg_path in &registry_paths {
-        if let Ok(key) = hkcu.open_subkey(reg_path) {
-            if let Ok(path) = key.get_value::<String, _>("") {
-                if Path::new(&path).exists() {
-                    return Some(path);
-                }
-            }
+        write_bundle(bundle.to_str().unwrap(), "page.html", "<p>hi</p>", &[(song, "assets/song.mp3".to_string())]).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&bundle).unwrap()).unwrap();
+        assert_eq!(archive.file_names().collect::<Vec<_>>().len(), 2);
+        for (name, contents) in [("page.html", "<p>hi</p>"), ("assets/song.mp3", "ID3 song")] {
+            let mut entry = archive.by_name(name).unwrap();
+            let mut text = String::new();
+            io::Read::read_to_string(&mut entry, &mut text).unwrap();
+            assert_eq!(text, contents);
         }
+        fs::remove_dir_all(&folder).unwrap();
     }
-    
-    None
-}
 
 
-fn main() {
+    #[test]
+    fn img_responsive_styles_every_image() {
+        let source = page("#gimmeh piccy cat.png a cat #mkay #maek showcase #gimmeh piccy dog.png a dog #mkay caption #oic");
+        let plain = compile_str(&source).unwrap();
+        assert!(plain.contains("<img src=\"cat.png\" alt=\"a cat\"/>") && !plain.contains("max-width"), "{}", plain);
 
-    //Collect all file arguments
-    let args: Vec<String> = env::args().collect();
+        let mut compiler = LolcodeCompiler::new();
+        compiler.img_responsive = config(&["--img-responsive", "page.lol"]).img_responsive;
+        let html = compiler.compile_to_html(&source).unwrap();
+        assert!(html.contains("<img src=\"cat.png\" alt=\"a cat\" style=\"max-width:100%;height:auto\"/>"), "{}", html);
+        assert!(html.contains("<img src=\"dog.png\" alt=\"a dog\" style=\"max-width:100%;height:auto\"/>"), "{}", html);
+    }
 
-    //Report error if problem parsing arguments
-    let config = Config::build(&args).unwrap_or_else(|err| {
-        println!("Problem parsing arguments: {err}");
-        process::exit(1);
-    });
 
-// Validate .lol extension
-let file_path = Path::new(&config.file_path);
-match file_path.extension().and_then(|ext| ext.to_str()) {
-    Some("lol") => {
-        //Continue
+    // The html of a document with the head and body given, compiled with a --default-title
+    fn titled(head: &str, body: &str) -> String {
+        let mut compiler = LolcodeCompiler::new();
+        compiler.default_title = config(&["--default-title", "Untitled & Co", "page.lol"]).default_title.unwrap();
+        compiler.compile_to_html(&format!("#hai #maek head {} #oic {} #kthxbye", head, body)).unwrap()
     }
-    //Error - wrong file extension
-    Some(other) => {
-        println!("Error: Invalid file extension '.{}'. Only .lol files are accepted.", other);
-        process::exit(1);
+
+    #[test]
+    fn title_is_kept_when_present() {
+        let html = titled("#gimmeh title My Page #mkay", "#maek bighead Welcome #oic");
+        assert!(html.contains("<meta charset=\"utf-8\">\n    <title>My Page</title>\n  </head>"), "{}", html);
     }
-    //Extension not found
-    None => {
-        println!("Error: No file extension found. Only .lol files are accepted.");
-        process::exit(1);
+
+    #[test]
+    fn missing_title_falls_back_to_the_first_heading() {
+        let html = titled("", "#i haz who #it iz Bob #mkay #maek paragraf intro #oic #maek medhead Hi #lemme see who #mkay #oic #maek bighead Later #oic");
+        assert!(html.contains("<meta charset=\"utf-8\">\n    <title>Hi Bob</title>\n  </head>"), "{}", html);
+
+        let html = titled("#gimmeh describ d #mkay #gimmeh title #mkay", "#maek lilhead A & B #oic");
+        assert!(html.contains("<meta name=\"description\" content=\"d\">\n    <title>A &amp; B</title>"), "{}", html);
     }
-}
 
-//Initialize html file at file path based on first name of .lol file in the same location
-let html_filename = file_path
-.file_stem()
-.and_then(|name| name.to_str())
-.map(|name| format!("{}.html", name))
-.unwrap_or_else(|| "output.html".to_string()); 
+    #[test]
+    fn page_without_title_or_headings_gets_the_default_title() {
+        let html = titled("", "");
+        assert!(html.contains("<meta charset=\"utf-8\">\n    <title>Untitled &amp; Co</title>\n  </head>"), "{}", html);
 
-//Read string from file and set into lolcode string
-    let lolcode_string: String;
-    match read_to_string(config.file_path) {
-        Ok(contents) => lolcode_string = contents,
+        let html = compile_str("#hai #maek head #oic #maek paragraf hi #oic #kthxbye").unwrap();
+        assert!(html.contains("<title></title>"), "{}", html);
+        assert!(Config::build(&args(&["page.lol", "--default-title"])).is_err());
+    }
 
-        //Report an error if not able to read file
-        Err(e) => {
-            println!("Error reading the file: {e}");
-            process::exit(1);
-        }
+
+    #[test]
+    fn soundz_with_two_addresses_is_one_audio_with_two_sources() {
+        let html = compile_str(&page("#gimmeh soundz a.mp3 b.ogg #mkay")).unwrap();
+        assert_eq!(html.matches("<audio controls>").count(), 1, "{}", html);
+        assert_eq!(html.matches("<source ").count(), 2, "{}", html);
+        assert!(
+            html.contains("<audio controls>\n      <source src=\"a.mp3\" type=\"audio/mpeg\">\n      <source src=\"b.ogg\" type=\"audio/ogg\">\n    </audio>"),
+            "{}",
+            html
+        );
     }
 
-    //Initialize a compiler
-    let mut compiler = LolcodeCompiler::new();
 
-    //Compile the file
-    compiler.compile(&lolcode_string);
+    #[test]
+    fn audio_type_comes_from_the_extension() {
+        let html = compile_str(&page("#gimmeh soundz song.OGG #mkay #gimmeh soundz clip.wav #mkay #gimmeh soundz noise.xyz #mkay #gimmeh soundz http://a.com/stream #mkay")).unwrap();
+        assert!(html.contains("<source src=\"song.OGG\" type=\"audio/ogg\">"), "{}", html);
+        assert!(html.contains("<source src=\"clip.wav\" type=\"audio/wav\">"), "{}", html);
+        assert!(html.contains("<source src=\"noise.xyz\" type=\"audio/mpeg\">"), "{}", html);
+        assert!(html.contains("<source src=\"http://a.com/stream\" type=\"audio/mpeg\">"), "{}", html);
+    }
 
-    //Parse the file
-    compiler.parse();
 
+    #[test]
+    fn spaces_between_words_are_collapsed() {
+        let html = compile_str(&page("#maek paragraf    Hello    World   #oic")).unwrap();
+        assert!(html.contains("<p>Hello World</p>"), "{}", html);
+    }
 
-    //Get the html string from file conversion and parsing
-    let html_string: String = compiler.to_html();
 
+    // The body elements with one more element, parsed as bold
+    fn body_gimmeh_elements_and_sparkle(lexer: &LolcodeLexicalAnalyzer) -> Vec<GimmehEntry<'_>> {
+        let mut table = LolcodeSyntaxAnalyzer::body_gimmeh_elements(lexer);
+        table.push((Box::leak(Box::new(vec!["sparkle".to_string()])), LolcodeSyntaxAnalyzer::parse_bold));
+        table
+    }
 
-    //Write the html to the file 
-    std::fs::write(&html_filename, html_string).expect("Unable to write file"); 
+    #[test]
+    fn unknown_gimmeh_element_lists_the_elements_of_the_table() {
+        let lexer = LolcodeLexicalAnalyzer::with_keywords("", KeywordConfig::default());
+        let table_keywords: Vec<String> = LolcodeSyntaxAnalyzer::body_gimmeh_elements(&lexer)
+            .iter()
+            .map(|(keywords, _parser)| keywords[0].clone())
+            .collect();
+        let error = compile_str(&page("#gimmeh bolt x #mkay")).unwrap_err();
+        assert!(matches!(&error, CompileError::Syntax { expected, found, .. } if expected == &table_keywords && found == "bolt"), "{:?}", error);
+
+        //An element added to the table is expected without touching the error
+        let mut compiler = LolcodeCompiler::new();
+        compiler.current_tok = "bolt".to_string();
+        let error = LolcodeSyntaxAnalyzer::new().parse_gimmeh_element(&mut compiler, body_gimmeh_elements_and_sparkle).unwrap_err();
+        assert!(
+            matches!(&error, CompileError::Syntax { expected, .. } if expected.len() == table_keywords.len() + 1 && expected.last().unwrap() == "sparkle"),
+            "{:?}",
+            error
+        );
 
-    //open the file in html
-    open_html_in_chrome(&html_filename); 
-    
-   
-}
\ No newline at end of file
+        //A renamed element is expected with its new keyword
+        let mut compiler = LolcodeCompiler::new();
+        compiler.keywords = KeywordConfig { bold_element: vec!["fat".into()], ..KeywordConfig::default() };
+        let errors = compiler.check(&page("#gimmeh bolt x #mkay")).unwrap_err();
+        assert!(matches!(&errors[0], CompileError::Syntax { expected, .. } if expected[0] == "fat" && !expected.contains(&"bold".to_string())), "{:?}", errors);
+    }
+
+
+    #[test]
+    fn dir_rejects_the_flags_of_a_single_file() {
+        for (flags, message) in [
+            (vec!["--dir", "src", "page.lol"], "a file argument cannot be used with --dir"),
+            (vec!["--dir", "src", "--bundle", "page.zip"], "--bundle cannot be used with --dir"),
+            (vec!["--dir", "src", "--json"], "--json cannot be used with --dir"),
+            (vec!["--stdout", "--dir", "src"], "--stdout cannot be used with --dir"),
+            (vec!["--dir", "src", "--dump-tokens"], "--dump-tokens cannot be used with --dir"),
+            (vec!["--dir", "src", "-o", "page.html"], "--output cannot be used with --dir, use --out-dir"),
+        ] {
+            assert!(matches!(Config::build(&args(&flags)), Err(error) if error == message), "{:?}", flags);
+        }
+        let batch = config(&["--dir", "src", "--out-dir", "out", "--minify", "--no-open"]);
+        assert_eq!(batch.dir_path.as_deref(), Some("src"));
+        assert!(batch.minify && batch.file_path.is_empty());
+    }
+
+    #[test]
+    fn batch_files_are_compiled_with_the_options_of_the_config() {
+        let folder = env::temp_dir().join(format!("lolcode_batch_{}", process::id()));
+        fs::create_dir_all(folder.join("out")).unwrap();
+        fs::write(folder.join("good.lol"), page("#maek paragraf hi #oic #gimmeh soundz s.mp3 #mkay")).unwrap();
+        fs::write(folder.join("s.mp3"), b"lol").unwrap();
+        fs::write(folder.join("bad.lol"), page("#gimmeh bolt x #mkay")).unwrap();
+        let batch = config(&["--dir", folder.to_str().unwrap(), "--minify", "--inline-media"]);
+
+        compile_batch_file(&batch, &folder.join("good.lol"), &folder.join("out")).unwrap();
+        let html = fs::read_to_string(folder.join("out/good.html")).unwrap();
+        assert!(!html.contains("\n  ") && html.contains("<p>hi</p>"), "{}", html);
+        assert!(html.contains("data:audio/mpeg;base64,bG9s"), "{}", html);
+
+        let messages = compile_batch_file(&batch, &folder.join("bad.lol"), &folder.join("out")).unwrap_err();
+        assert!(!folder.join("out/bad.html").exists());
+        fs::remove_dir_all(&folder).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].starts_with("Syntax error at line 1") && messages[0].ends_with("found 'bolt'."), "{:?}", messages);
+    }
+}